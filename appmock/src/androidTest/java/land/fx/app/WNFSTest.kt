@@ -7,6 +7,10 @@ import androidx.test.ext.junit.rules.ActivityScenarioRule
 import fulamobile.Fulamobile
 import land.fx.wnfslib.Fs.*
 import land.fx.wnfslib.Config
+import land.fx.wnfslib.CancellationToken
+import land.fx.wnfslib.Entry
+import land.fx.wnfslib.ProgressListener
+import land.fx.wnfslib.UploadOptions
 import land.fx.wnfslib.result.*
 import land.fx.wnfslib.exceptions.*
 import org.junit.Assert.*
@@ -115,6 +119,116 @@ interface Datastore {
     fun get(cid: ByteArray): ByteArray
 }
 
+/** Stores blocks like InMemoryDatastore but acknowledges puts with an empty CID once armed. */
+class EmptyPutDatastore : land.fx.wnfslib.Datastore {
+    private val inner = InMemoryDatastore()
+    var armed = false
+
+    override fun put(cid: ByteArray, data: ByteArray): ByteArray {
+        val returned = inner.put(cid, data)
+        return if (armed) byteArrayOf() else returned
+    }
+
+    override fun get(cid: ByteArray): ByteArray {
+        return inner.get(cid)
+    }
+}
+
+/** Behaves like InMemoryDatastore until it goes offline, then returns null or throws from get. Puts throw while failPuts is set. */
+class OfflineDatastore : land.fx.wnfslib.Datastore {
+    private val inner = InMemoryDatastore()
+    var offline = false
+    var throwWhenOffline = false
+    var failPuts = false
+
+    override fun put(cid: ByteArray, data: ByteArray): ByteArray {
+        if (failPuts) {
+            throw java.io.IOException("offline")
+        }
+        return inner.put(cid, data)
+    }
+
+    override fun get(cid: ByteArray): ByteArray? {
+        if (offline) {
+            if (throwWhenOffline) {
+                throw java.io.IOException("offline")
+            }
+            return null
+        }
+        return inner.get(cid)
+    }
+}
+
+/** Acknowledges but does not keep large blocks while armed, leaving file content unreadable. */
+class DroppingDatastore : land.fx.wnfslib.Datastore {
+    private val inner = InMemoryDatastore()
+    var armed = false
+
+    override fun put(cid: ByteArray, data: ByteArray): ByteArray {
+        if (armed && data.size > 100_000) {
+            return cid
+        }
+        return inner.put(cid, data)
+    }
+
+    override fun get(cid: ByteArray): ByteArray? {
+        val data = inner.get(cid)
+        return if (data.isEmpty()) null else data
+    }
+}
+
+/** Stores blocks like InMemoryDatastore, answers has() and hasMany() and counts the checks. */
+class IndexingDatastore : land.fx.wnfslib.IndexedDatastore {
+    private val inner = InMemoryDatastore()
+    private val cids = ConcurrentHashMap.newKeySet<String>()
+    var checks = 0
+
+    override fun put(cid: ByteArray, data: ByteArray): ByteArray {
+        cids.add(Base64.getEncoder().encodeToString(cid))
+        return inner.put(cid, data)
+    }
+
+    override fun get(cid: ByteArray): ByteArray {
+        return inner.get(cid)
+    }
+
+    override fun has(cid: ByteArray): Boolean {
+        checks++
+        return cids.contains(Base64.getEncoder().encodeToString(cid))
+    }
+
+    override fun hasMany(cids: Array<ByteArray>): BooleanArray {
+        return BooleanArray(cids.size) { has(cids[it]) }
+    }
+}
+
+/** Stores blocks like InMemoryDatastore and counts single-block and batched puts. */
+class BatchingDatastore : land.fx.wnfslib.BatchDatastore {
+    private val inner = InMemoryDatastore()
+    var puts = 0
+    var putManyCalls = 0
+    var blocksPut = 0
+
+    override fun put(cid: ByteArray, data: ByteArray): ByteArray {
+        puts++
+        return inner.put(cid, data)
+    }
+
+    override fun get(cid: ByteArray): ByteArray {
+        return inner.get(cid)
+    }
+
+    override fun putMany(cids: Array<ByteArray>, data: Array<ByteArray>): Array<ByteArray> {
+        putManyCalls++
+        blocksPut += cids.size
+        return Array(cids.size) { inner.put(cids[it], data[it]) }
+    }
+
+    override fun getMany(cids: Array<ByteArray>): Array<ByteArray?> {
+        return Array(cids.size) { i -> inner.get(cids[i]).takeIf { it.isNotEmpty() } }
+    }
+}
+
 private fun generateLargeTestFile(path: String): File {
     val file = File(path, "largeTestFile.txt")
 
@@ -437,4 +551,684 @@ class WNFSTest {
         Log.d("AppMock", "Clean up done.")
 
     }
+
+    @Test
+    fun wnfs_session() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+
+        val session = openSession(client, config.cid, wnfsKey)
+        try {
+            config = mkdir(session, "root/album")
+            assertNotNull("cid should not be null", config.cid)
+            config = writeFile(session, "root/album/a.txt", "Hello, Session!".toByteArray())
+            config = cp(session, "root/album/a.txt", "root/album/b.txt")
+            config = mv(session, "root/album/b.txt", "root/album/c.txt")
+            config = rm(session, "root/album/a.txt")
+
+            val content = readFile(session, "root/album/c.txt")
+            assert(content contentEquals "Hello, Session!".toByteArray())
+
+            val fileNames = String(ls(session, "root/album"))
+            Log.d("AppMock", "session ls. fileNames=$fileNames")
+            assertTrue(fileNames.contains("c.txt"))
+            assertFalse(fileNames.contains("a.txt"))
+        } finally {
+            close(session)
+        }
+
+        // The root returned by the session is readable through the stateless API
+        val content = readFile(client, config.cid, "root/album/c.txt")
+        assert(content contentEquals "Hello, Session!".toByteArray())
+
+        try {
+            readFile(session, "root/album/c.txt")
+            fail("closed session should not be usable")
+        } catch (e: Exception) {
+            assertEquals(e.message?.contains("session", true), true)
+        }
+    }
+
+    @Test
+    fun wnfs_put_mismatch() {
+        initRustLogger()
+        val client = EmptyPutDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        val config: Config = init(client, wnfsKey)
+
+        client.armed = true
+        try {
+            writeFile(client, config.cid, "root/test.txt", "Hello, World!".toByteArray())
+            fail("writeFile should fail when the datastore does not return the stored CID")
+        } catch (e: Exception) {
+            Log.d("AppMock", "put mismatch. error=" + e.message)
+            assertEquals(e.message?.contains("store mismatch", true), true)
+            assertTrue(e is StoreMismatchException)
+        }
+    }
+
+    @Test
+    fun wnfs_get_unavailable() {
+        initRustLogger()
+        val client = OfflineDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        config = writeFile(client, config.cid, "root/test.txt", "Hello, World!".toByteArray())
+
+        client.offline = true
+        for (throwWhenOffline in listOf(false, true)) {
+            client.throwWhenOffline = throwWhenOffline
+            try {
+                readFile(client, config.cid, "root/test.txt")
+                fail("readFile should fail while the datastore is offline")
+            } catch (e: Exception) {
+                Log.d("AppMock", "get unavailable. error=" + e.message)
+                assertEquals(e.message?.contains("block not available", true), true)
+                assertTrue(e is StoreUnavailableException)
+                assertEquals((e as WnfsException).code, ErrorCode.STORE_UNAVAILABLE)
+            }
+        }
+
+        client.offline = false
+        val content = readFile(client, config.cid, "root/test.txt")
+        assert(content contentEquals "Hello, World!".toByteArray())
+    }
+
+    @Test
+    fun wnfs_garbage_input() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        val config: Config = init(client, wnfsKey)
+        val badCid = "not-a-cid"
+        val local = InstrumentationRegistry.getInstrumentation().targetContext.cacheDir.path + "/garbage.txt"
+
+        val badCidCalls: List<Pair<String, () -> Any?>> = listOf(
+            "loadWithWNFSKey" to { loadWithWNFSKey(client, wnfsKey, badCid) },
+            "writeFileFromPath" to { writeFileFromPath(client, badCid, "root/a.txt", local) },
+            "writeFileStreamFromPath" to { writeFileStreamFromPath(client, badCid, "root/a.txt", local) },
+            "writeFile" to { writeFile(client, badCid, "root/a.txt", byteArrayOf(1)) },
+            "ls" to { ls(client, badCid, "root") },
+            "mkdir" to { mkdir(client, badCid, "root/dir") },
+            "rm" to { rm(client, badCid, "root/a.txt") },
+            "mv" to { mv(client, badCid, "root/a.txt", "root/b.txt") },
+            "cp" to { cp(client, badCid, "root/a.txt", "root/b.txt") },
+            "readFileToPath" to { readFileToPath(client, badCid, "root/a.txt", local) },
+            "readFilestreamToPath" to { readFilestreamToPath(client, badCid, "root/a.txt", local) },
+            "readFile" to { readFile(client, badCid, "root/a.txt") },
+            "openSession" to { openSession(client, badCid, wnfsKey) },
+        )
+        for ((name, call) in badCidCalls) {
+            try {
+                call()
+                fail("$name should fail for a malformed cid")
+            } catch (e: InvalidCidException) {
+                Log.d("AppMock", "$name with bad cid. error=" + e.message)
+            }
+        }
+
+        val nullCalls: List<Pair<String, () -> Any?>> = listOf(
+            "init" to { init(client, null) },
+            "loadWithWNFSKey" to { loadWithWNFSKey(client, null, null) },
+            "writeFileFromPath" to { writeFileFromPath(client, config.cid, null, null) },
+            "writeFileStreamFromPath" to { writeFileStreamFromPath(client, config.cid, "root/a.txt", null) },
+            "writeFile" to { writeFile(client, config.cid, "root/a.txt", null) },
+            "ls" to { ls(client, null, null) },
+            "mkdir" to { mkdir(null, config.cid, "root/dir") },
+            "rm" to { rm(client, config.cid, null) },
+            "mv" to { mv(client, config.cid, null, "root/b.txt") },
+            "cp" to { cp(client, config.cid, "root/a.txt", null) },
+            "readFileToPath" to { readFileToPath(client, config.cid, null, local) },
+            "readFilestreamToPath" to { readFilestreamToPath(client, config.cid, "root/a.txt", null) },
+            "readFile" to { readFile(client, null, "root/a.txt") },
+            "openSession" to { openSession(client, config.cid, null) },
+            "close" to { close(-1) },
+            "session readFile" to { readFile(-1, "root/a.txt") },
+            "session writeFile" to { writeFile(-1, null, null) },
+        )
+        for ((name, call) in nullCalls) {
+            try {
+                call()
+                fail("$name should fail for null input")
+            } catch (e: WnfsException) {
+                Log.d("AppMock", "$name with null input. error=" + e.message)
+            }
+        }
+
+        // The process survived; the forest is still usable.
+        val fileNames = ls(client, config.cid, "/")
+        assertNotNull(fileNames)
+    }
+
+    @Test
+    fun wnfs_error_codes() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        config = writeFile(client, config.cid, "root/test.txt", "Hello, World!".toByteArray())
+
+        try {
+            readFile(client, config.cid, "root/missing.txt")
+            fail("readFile of a missing path should fail")
+        } catch (e: NotFoundException) {
+            assertEquals(e.code, ErrorCode.NOT_FOUND)
+        }
+
+        try {
+            ls(client, config.cid, "root/test.txt/child")
+            fail("ls below a file should fail")
+        } catch (e: WnfsException) {
+            Log.d("AppMock", "ls below a file. code=" + e.code + " error=" + e.message)
+            assertNotEquals(e.code, ErrorCode.STORE_UNAVAILABLE)
+        }
+    }
+
+    @Test
+    fun wnfs_ls_entries() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        // Names that used to collide with the old ???/!!! separators
+        config = writeFile(client, config.cid, "root/what???!!!.txt", "Hello, World!".toByteArray())
+        config = mkdir(client, config.cid, "root/album")
+
+        val entries = lsEntries(client, config.cid, "root").associateBy { it.name }
+        Log.d("AppMock", "lsEntries. entries=" + entries.keys)
+        assertEquals(2, entries.size)
+
+        val file = entries.getValue("what???!!!.txt")
+        assertEquals(Entry.Kind.FILE, file.kind)
+        assertEquals(13L, file.size)
+        assertNotNull(file.cid)
+        assertNotNull(file.modified)
+
+        val dir = entries.getValue("album")
+        assertTrue(dir.isDirectory)
+        assertEquals(0L, dir.size)
+
+        val session = openSession(client, config.cid, wnfsKey)
+        try {
+            val sessionEntries = lsEntries(session, "root")
+            assertEquals(entries.keys, sessionEntries.map { it.name }.toSet())
+        } finally {
+            close(session)
+        }
+    }
+
+    @Test
+    fun wnfs_stat() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        config = writeFile(client, config.cid, "root/test.txt", "Hello, World!".toByteArray())
+
+        val fileStat = stat(client, config.cid, "root/test.txt")
+        assertEquals(Entry.Kind.FILE, fileStat.kind)
+        assertEquals(13L, fileStat.size)
+        assertNotNull(fileStat.created)
+        assertNotNull(fileStat.modified)
+        assertNotNull(fileStat.cid)
+
+        val dirStat = stat(client, config.cid, "root")
+        assertTrue(dirStat.isDirectory)
+        assertEquals(1L, dirStat.size)
+
+        // Rewriting the file gives it a new content CID
+        config = writeFile(client, config.cid, "root/test.txt", "Hello, again!".toByteArray())
+        assertNotEquals(fileStat.cid, stat(client, config.cid, "root/test.txt").cid)
+
+        try {
+            stat(client, config.cid, "root/missing.txt")
+            fail("stat of a missing path should fail")
+        } catch (e: NotFoundException) {
+            assertEquals(e.code, ErrorCode.NOT_FOUND)
+        }
+    }
+
+    @Test
+    fun wnfs_exists() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        config = writeFile(client, config.cid, "root/test.txt", "Hello, World!".toByteArray())
+
+        assertEquals(Entry.Kind.FILE, exists(client, config.cid, "root/test.txt"))
+        assertEquals(Entry.Kind.DIRECTORY, exists(client, config.cid, "root"))
+        assertNull(exists(client, config.cid, "root/missing.txt"))
+        assertNull(exists(client, config.cid, "missing/dir/test.txt"))
+    }
+
+    @Test
+    fun wnfs_read_range() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        val content = ByteArray(3 * 1024 * 1024) { (it % 251).toByte() }
+        config = writeFile(client, config.cid, "root/video.bin", content)
+
+        val range = readFileRange(client, config.cid, "root/video.bin", 1_000_000, 4096)
+        assert(range contentEquals content.copyOfRange(1_000_000, 1_004_096))
+
+        // Past the end of the file the range is cut short
+        val tail = readFileRange(client, config.cid, "root/video.bin", content.size - 10L, 4096)
+        assert(tail contentEquals content.copyOfRange(content.size - 10, content.size))
+
+        // A small range must not pull the whole file through the datastore
+        val beforeRange = client.getTotalBytesGet()
+        readFileRange(client, config.cid, "root/video.bin", 2_000_000, 16)
+        val rangeBytes = client.getTotalBytesGet() - beforeRange
+        val beforeFull = client.getTotalBytesGet()
+        readFile(client, config.cid, "root/video.bin")
+        val fullBytes = client.getTotalBytesGet() - beforeFull
+        Log.d("AppMock", "read_range. rangeBytes=$rangeBytes fullBytes=$fullBytes")
+        assertTrue(rangeBytes < fullBytes / 2)
+
+        // Resume a download that stopped part way through
+        val local = File(appContext.cacheDir, "video_resume.bin")
+        local.writeBytes(content.copyOfRange(0, 1_500_000))
+        readFilestreamToPath(client, config.cid, "root/video.bin", local.path, local.length())
+        assert(local.readBytes() contentEquals content)
+        local.delete()
+
+        try {
+            readFileRange(client, config.cid, "root/video.bin", -1, 16)
+            fail("negative offset should fail")
+        } catch (e: WnfsException) {
+            Log.d("AppMock", "negative offset. error=" + e.message)
+        }
+    }
+
+    @Test
+    fun wnfs_write_dir_from_path() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+
+        val localDir = File(appContext.cacheDir, "camera_" + UUID.randomUUID())
+        File(localDir, "2024/trip").mkdirs()
+        File(localDir, "a.jpg").writeText("a")
+        File(localDir, "2024/b.jpg").writeText("bb")
+        File(localDir, "2024/trip/c.jpg").writeText("ccc")
+        File(localDir, ".nomedia").writeText("")
+
+        val report = writeDirFromPath(client, config.cid, "root/camera", localDir.path, UploadOptions(true, false))
+        Log.d("AppMock", "writeDirFromPath. succeeded=" + report.succeeded + " failed=" + report.failed.map { it.path })
+        assertEquals(listOf("2024/b.jpg", "2024/trip/c.jpg", "a.jpg"), report.succeeded.sorted())
+        assertTrue(report.failed.isEmpty())
+        config = report.config!!
+
+        assertEquals("ccc", String(readFile(client, config.cid, "root/camera/2024/trip/c.jpg")))
+        assertNull(exists(client, config.cid, "root/camera/.nomedia"))
+
+        // A second run skips what is already there unless asked to overwrite
+        File(localDir, "a.jpg").writeText("changed")
+        val rerun = writeDirFromPath(client, config.cid, "root/camera", localDir.path, UploadOptions(true, false))
+        assertTrue(rerun.succeeded.isEmpty())
+        assertEquals(3, rerun.skipped.size)
+        val overwrite = writeDirFromPath(client, rerun.config!!.cid, "root/camera", localDir.path, UploadOptions(true, true))
+        assertEquals("changed", String(readFile(client, overwrite.config!!.cid, "root/camera/a.jpg")))
+
+        localDir.deleteRecursively()
+    }
+
+    @Test
+    fun wnfs_read_dir_to_path() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val client = DroppingDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        config = writeFile(client, config.cid, "root/album/a.txt", "a".toByteArray())
+        config = writeFile(client, config.cid, "root/album/2024/b.txt", "bb".toByteArray())
+        client.armed = true
+        config = writeFile(client, config.cid, "root/album/big.bin", ByteArray(1024 * 1024) { it.toByte() })
+        client.armed = false
+
+        val localDir = File(appContext.cacheDir, "album_" + UUID.randomUUID())
+        val report = readDirToPath(client, config.cid, "root/album", localDir.path)
+        Log.d("AppMock", "readDirToPath. succeeded=" + report.succeeded + " failed=" + report.failed.map { it.path })
+        assertNull(report.config)
+        assertEquals(listOf("2024/b.txt", "a.txt"), report.succeeded.sorted())
+        assertEquals(listOf("big.bin"), report.failed.map { it.path })
+        assertEquals(ErrorCode.STORE_UNAVAILABLE, report.failed[0].code)
+
+        assertEquals("bb", File(localDir, "2024/b.txt").readText())
+        assertFalse(File(localDir, "big.bin").exists())
+        val modified = stat(client, config.cid, "root/album/a.txt").modified!!
+        assertEquals(modified, File(localDir, "a.txt").lastModified() / 1000)
+
+        localDir.deleteRecursively()
+    }
+
+    @Test
+    fun wnfs_transaction() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        val initial: Config = init(client, wnfsKey)
+
+        val tx = begin(client, initial.cid).mkdir("root/album")
+        for (i in 1..20) {
+            tx.writeFile("root/album/$i.jpg", "photo $i".toByteArray())
+        }
+        tx.mv("root/album/1.jpg", "root/album/cover.jpg")
+        assertEquals(22, tx.size())
+        val config = tx.commit()
+
+        val names = lsEntries(client, config.cid, "root/album").map { it.name }.toSet()
+        assertEquals(20, names.size)
+        assertTrue(names.contains("cover.jpg"))
+        assertFalse(names.contains("1.jpg"))
+        assertEquals("photo 1", String(readFile(client, config.cid, "root/album/cover.jpg")))
+
+        // A failing step commits nothing; both roots stay as they were
+        try {
+            begin(client, config.cid)
+                .writeFile("root/album/21.jpg", "photo 21".toByteArray())
+                .rm("root/album/missing.jpg")
+                .commit()
+            fail("transaction with a failing step should not commit")
+        } catch (e: WnfsException) {
+            Log.d("AppMock", "transaction rollback. error=" + e.message)
+            assertEquals(e.message?.contains("nothing was committed", true), true)
+        }
+        assertNull(exists(client, config.cid, "root/album/21.jpg"))
+        assertNull(exists(client, initial.cid, "root/album"))
+    }
+
+    @Test
+    fun wnfs_progress() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+
+        val local = File(appContext.cacheDir, "progress_" + UUID.randomUUID())
+        local.writeBytes(ByteArray(4 * 1024 * 1024) { (it % 253).toByte() })
+
+        val uploads = mutableListOf<Pair<Long, Long>>()
+        config = writeFileStreamFromPath(client, config.cid, "root/video.bin", local.path,
+            ProgressListener { processed, total -> uploads.add(processed to total) }, 0)
+        Log.d("AppMock", "upload progress calls=" + uploads.size)
+        assertTrue(uploads.size > 1)
+        assertEquals(local.length() to local.length(), uploads.last())
+        assertEquals(uploads.map { it.first }.sorted(), uploads.map { it.first })
+
+        val downloaded = File(appContext.cacheDir, "progress_download_" + UUID.randomUUID())
+        val downloads = mutableListOf<Pair<Long, Long>>()
+        readFilestreamToPath(client, config.cid, "root/video.bin", downloaded.path, 0,
+            ProgressListener { processed, total -> downloads.add(processed to total) }, 0)
+        assertTrue(downloads.size > 1)
+        assertEquals(local.length() to local.length(), downloads.last())
+        assert(downloaded.readBytes() contentEquals local.readBytes())
+
+        // A long interval still reports completion
+        val throttled = mutableListOf<Pair<Long, Long>>()
+        readFilestreamToPath(client, config.cid, "root/video.bin", downloaded.path, 0,
+            ProgressListener { processed, total -> throttled.add(processed to total) }, 60_000)
+        assertTrue(throttled.size <= 2)
+        assertEquals(local.length(), throttled.last().first)
+
+        local.delete()
+        downloaded.delete()
+    }
+
+    @Test
+    fun wnfs_cancellation() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        val local = File(appContext.cacheDir, "cancel_" + UUID.randomUUID())
+        local.writeBytes(ByteArray(4 * 1024 * 1024) { (it % 253).toByte() })
+
+        // Cancel an upload part way through; the previous root stays usable
+        val uploadToken = CancellationToken()
+        try {
+            writeFileStreamFromPath(client, config.cid, "root/video.bin", local.path,
+                ProgressListener { processed, _ -> if (processed > 1024 * 1024) uploadToken.cancel() }, 0, uploadToken)
+            fail("cancelled upload should fail")
+        } catch (e: CancelledException) {
+            assertEquals(ErrorCode.CANCELLED, e.code)
+        }
+        assertNull(exists(client, config.cid, "root/video.bin"))
+
+        config = writeFileStreamFromPath(client, config.cid, "root/video.bin", local.path)
+
+        // Cancel a download, then resume it from what was written
+        val downloaded = File(appContext.cacheDir, "cancel_download_" + UUID.randomUUID())
+        val downloadToken = CancellationToken()
+        try {
+            readFilestreamToPath(client, config.cid, "root/video.bin", downloaded.path, 0,
+                ProgressListener { _, _ -> downloadToken.cancel() }, 0, downloadToken)
+            fail("cancelled download should fail")
+        } catch (e: CancelledException) {
+            assertEquals(ErrorCode.CANCELLED, e.code)
+        }
+        assertTrue(downloaded.length() < local.length())
+        readFilestreamToPath(client, config.cid, "root/video.bin", downloaded.path, downloaded.length())
+        assert(downloaded.readBytes() contentEquals local.readBytes())
+
+        local.delete()
+        downloaded.delete()
+    }
+
+    @Test
+    fun wnfs_session_cache() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        val content = ByteArray(512 * 1024) { (it % 251).toByte() }
+        config = writeFile(client, config.cid, "root/photo.jpg", content)
+
+        val session = openSession(client, config.cid, wnfsKey, 8L * 1024 * 1024)
+        try {
+            assert(readFile(session, "root/photo.jpg") contentEquals content)
+            val bytesGetAfterFirstRead = client.getTotalBytesGet()
+            assert(readFile(session, "root/photo.jpg") contentEquals content)
+            // The second read is served from the session cache
+            assertEquals(bytesGetAfterFirstRead, client.getTotalBytesGet())
+
+            val stats = getCacheStats(session)
+            Log.d("AppMock", "cache stats. hits=${stats.hits} misses=${stats.misses} size=${stats.size}")
+            assertEquals(8L * 1024 * 1024, stats.capacity)
+            assertTrue(stats.hits > 0)
+            assertTrue(stats.misses > 0)
+            assertTrue(stats.size <= stats.capacity)
+        } finally {
+            close(session)
+        }
+
+        // Sessions opened without a size have no cache
+        val uncached = openSession(client, config.cid, wnfsKey)
+        try {
+            readFile(uncached, "root/photo.jpg")
+            assertEquals(0L, getCacheStats(uncached).hits)
+        } finally {
+            close(uncached)
+        }
+    }
+
+    @Test
+    fun wnfs_disk_cache() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val cacheDir = File(appContext.filesDir, "blocks_" + UUID.randomUUID())
+        val cacheKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("cache".toByteArray(StandardCharsets.UTF_8))
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        enableDiskCache(cacheDir.path, 16L * 1024 * 1024, cacheKey)
+        try {
+            val client = InMemoryDatastore()
+            var config: Config = init(client, wnfsKey)
+            config = writeFile(client, config.cid, "root/a.txt", "hello".toByteArray())
+
+            // Reopening the cache, as after a restart, keeps what was written
+            disableDiskCache()
+            enableDiskCache(cacheDir.path, 16L * 1024 * 1024, cacheKey)
+            val empty = InMemoryDatastore()
+            assert(readFile(empty, config.cid, "root/a.txt") contentEquals "hello".toByteArray())
+            assertEquals(0L, empty.getTotalBytesGet())
+
+            val stats = getDiskCacheStats()
+            Log.d("AppMock", "disk cache stats. hits=${stats.hits} misses=${stats.misses} size=${stats.size}")
+            assertTrue(stats.hits > 0)
+            assertTrue(stats.entries > 0)
+
+            clearCache()
+            assertEquals(0L, getDiskCacheStats().entries)
+            try {
+                readFile(empty, config.cid, "root/a.txt")
+                fail("expected the cleared cache to miss")
+            } catch (e: WnfsException) {
+                assertEquals(ErrorCode.STORE_UNAVAILABLE, e.code)
+            }
+        } finally {
+            disableDiskCache()
+            cacheDir.deleteRecursively()
+        }
+        assertEquals(0L, getDiskCacheStats().capacity)
+    }
+
+    @Test
+    fun wnfs_batched_datastore() {
+        initRustLogger()
+        val client = BatchingDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        val content = ByteArray(4 * 1024 * 1024) { (it % 251).toByte() }
+        config = writeFile(client, config.cid, "root/video.mp4", content)
+        Log.d("AppMock", "batched puts. putMany=${client.putManyCalls} blocks=${client.blocksPut} puts=${client.puts}")
+
+        assertEquals(0, client.puts)
+        assertTrue(client.putManyCalls > 0)
+        assertTrue(client.putManyCalls < client.blocksPut)
+        assert(readFile(client, config.cid, "root/video.mp4") contentEquals content)
+    }
+
+    @Test
+    fun wnfs_put_policy() {
+        initRustLogger()
+        val client = IndexingDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        config = writeFile(client, config.cid, "root/a.txt", "first".toByteArray())
+        assertEquals(0, client.checks)
+
+        setPutPolicy(land.fx.wnfslib.PutPolicy.CHECK_FIRST)
+        try {
+            config = writeFile(client, config.cid, "root/b.txt", "second".toByteArray())
+            val stats = getPutStats()
+            Log.d("AppMock", "put stats. puts=${stats.puts} checks=${stats.checks} skipped=${stats.skipped} skippedBytes=${stats.skippedBytes}")
+            assertTrue(client.checks > 0)
+            assertTrue(stats.checks >= client.checks)
+        } finally {
+            setPutPolicy(land.fx.wnfslib.PutPolicy.ALWAYS)
+        }
+        assert(readFile(client, config.cid, "root/a.txt") contentEquals "first".toByteArray())
+        assert(readFile(client, config.cid, "root/b.txt") contentEquals "second".toByteArray())
+    }
+
+    @Test
+    fun wnfs_write_back_session() {
+        initRustLogger()
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        val config: Config = init(client, wnfsKey)
+        val bytesPutBefore = client.getTotalBytesPut()
+
+        val session = openSession(client, config.cid, wnfsKey, 0, true)
+        val flushed: Config
+        try {
+            mkdir(session, "root/notes")
+            for (draft in listOf("first draft", "second draft", "final")) {
+                writeFile(session, "root/notes/todo.txt", draft.toByteArray())
+            }
+            // Nothing has reached the Datastore yet, but the session reads its own writes
+            assertEquals(bytesPutBefore, client.getTotalBytesPut())
+            assert(readFile(session, "root/notes/todo.txt") contentEquals "final".toByteArray())
+            flushed = flush(session)
+        } finally {
+            close(session)
+        }
+        Log.d("AppMock", "write-back flush put ${client.getTotalBytesPut() - bytesPutBefore} bytes")
+        assertTrue(client.getTotalBytesPut() > bytesPutBefore)
+        assert(readFile(client, flushed.cid, "root/notes/todo.txt") contentEquals "final".toByteArray())
+    }
+
+    @Test
+    fun wnfs_upload_queue() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val queueDir = File(appContext.filesDir, "uploads_" + UUID.randomUUID())
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        val client = OfflineDatastore()
+        var config: Config = init(client, wnfsKey)
+        enableUploadQueue(queueDir.path)
+        try {
+            client.failPuts = true
+            config = writeFile(client, config.cid, "root/offline.txt", "written offline".toByteArray())
+            val pending = pendingUploads()
+            Log.d("AppMock", "pending uploads. blocks=${pending.blocks} bytes=${pending.bytes}")
+            assertTrue(pending.blocks > 0)
+
+            // The queue survives being reopened, as after a restart
+            disableUploadQueue()
+            enableUploadQueue(queueDir.path)
+            assertEquals(pending.blocks, pendingUploads().blocks)
+
+            try {
+                syncPending(client)
+                fail("expected the sync to fail while offline")
+            } catch (e: WnfsException) {
+                assertEquals(ErrorCode.STORE_UNAVAILABLE, e.code)
+            }
+            assertEquals(pending.blocks, pendingUploads().blocks)
+
+            client.failPuts = false
+            assertEquals(pending.blocks, syncPending(client))
+            assertEquals(0L, pendingUploads().blocks)
+        } finally {
+            disableUploadQueue()
+            queueDir.deleteRecursively()
+        }
+        assert(readFile(client, config.cid, "root/offline.txt") contentEquals "written offline".toByteArray())
+    }
+
+    @Test
+    fun wnfs_export_car() {
+        initRustLogger()
+        val appContext = InstrumentationRegistry.getInstrumentation().targetContext
+        val client = InMemoryDatastore()
+        val wnfsKey: ByteArray = MessageDigest.getInstance("SHA-256").digest("test".toByteArray(StandardCharsets.UTF_8))
+        var config: Config = init(client, wnfsKey)
+        config = writeFile(client, config.cid, "root/docs/a.txt", "inside".toByteArray())
+        config = writeFile(client, config.cid, "root/b.txt", ByteArray(300_000) { 7 })
+
+        val forestCar = File(appContext.filesDir, "forest_" + UUID.randomUUID() + ".car")
+        val docsCar = File(appContext.filesDir, "docs_" + UUID.randomUUID() + ".car")
+        try {
+            val blocks = exportCar(client, config.cid, forestCar.path)
+            val docsBlocks = exportCar(client, config.cid, docsCar.path, "root/docs")
+            Log.d("AppMock", "car export. forest=$blocks blocks, ${forestCar.length()} bytes; docs=$docsBlocks blocks, ${docsCar.length()} bytes")
+            assertTrue(docsBlocks in 1 until blocks)
+            assertTrue(forestCar.length() > 300_000)
+            assertTrue(docsCar.length() < 300_000)
+        } finally {
+            forestCar.delete()
+            docsCar.delete()
+        }
+    }
 }
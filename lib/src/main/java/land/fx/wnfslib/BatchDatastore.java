@@ -0,0 +1,14 @@
+package land.fx.wnfslib;
+
+/**
+ * A Datastore that can move several blocks per call. When the Datastore given to
+ * Fs implements these methods, the blocks an operation writes are handed over in
+ * batches instead of one put per block. Plain Datastores keep working unchanged.
+ */
+public interface BatchDatastore extends Datastore {
+    /** Stores data[i] under cids[i] and returns the CIDs they were stored under, in the same order. */
+    byte[][] putMany(byte[][] cids, byte[][] data);
+
+    /** Returns the block of each CID, in the same order; null for blocks that are not available. */
+    byte[][] getMany(byte[][] cids);
+}
@@ -0,0 +1,66 @@
+package land.fx.wnfslib;
+
+import androidx.annotation.NonNull;
+
+import java.nio.charset.StandardCharsets;
+import org.json.JSONException;
+import org.json.JSONObject;
+
+/**
+ * Counters of a block cache: a session's in-memory cache, see
+ * {@link Fs#openSession(Datastore, String, byte[], long)}, or the persistent one,
+ * see {@link Fs#enableDiskCache}.
+ */
+public final class CacheStats {
+    private final long capacity;
+    private final long size;
+    private final long entries;
+    private final long hits;
+    private final long misses;
+
+    public CacheStats(long capacity, long size, long entries, long hits, long misses) {
+        super();
+        this.capacity = capacity;
+        this.size = size;
+        this.entries = entries;
+        this.hits = hits;
+        this.misses = misses;
+    }
+
+    /** Maximum bytes the cache holds; 0 if there is no cache. */
+    public long getCapacity() {
+        return this.capacity;
+    }
+
+    /** Bytes currently held, counting CIDs and block data. */
+    public long getSize() {
+        return this.size;
+    }
+
+    public long getEntries() {
+        return this.entries;
+    }
+
+    /** Block reads answered from the cache. */
+    public long getHits() {
+        return this.hits;
+    }
+
+    /** Block reads that went to the Datastore. */
+    public long getMisses() {
+        return this.misses;
+    }
+
+    /** Parses the JSON object returned by {@link Fs#getCacheStats} and {@link Fs#getDiskCacheStats}. */
+    @NonNull
+    public static CacheStats parse(@NonNull byte[] statsOutput) throws JSONException {
+        JSONObject obj = new JSONObject(new String(statsOutput, StandardCharsets.UTF_8));
+        return new CacheStats(
+            obj.getLong("capacity"),
+            obj.getLong("size"),
+            obj.getLong("entries"),
+            obj.getLong("hits"),
+            obj.getLong("misses")
+        );
+    }
+}
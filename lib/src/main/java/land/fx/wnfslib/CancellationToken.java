@@ -0,0 +1,18 @@
+package land.fx.wnfslib;
+
+/**
+ * Lets another thread stop a streaming upload or download. The native side checks the token
+ * between chunks and fails the call with {@link land.fx.wnfslib.exceptions.CancelledException}.
+ * A token stays cancelled once tripped; use a new one for each call.
+ */
+public final class CancellationToken {
+    private volatile boolean cancelled = false;
+
+    public void cancel() {
+        this.cancelled = true;
+    }
+
+    public boolean isCancelled() {
+        return this.cancelled;
+    }
+}
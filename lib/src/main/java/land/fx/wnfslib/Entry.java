@@ -0,0 +1,95 @@
+package land.fx.wnfslib;
+
+import androidx.annotation.NonNull;
+import androidx.annotation.Nullable;
+
+import java.nio.charset.StandardCharsets;
+import java.util.ArrayList;
+import java.util.List;
+import org.json.JSONArray;
+import org.json.JSONException;
+import org.json.JSONObject;
+
+public final class Entry {
+    public enum Kind {
+        FILE,
+        DIRECTORY;
+
+        static Kind parse(String kind) {
+            return "directory".equals(kind) ? DIRECTORY : FILE;
+        }
+    }
+
+    private final String name;
+    private final Kind kind;
+    private final long size;
+    private final Long created;
+    private final Long modified;
+    private final String cid;
+
+    public Entry(String name, Kind kind, long size, Long created, Long modified, String cid) {
+        super();
+        this.name = name;
+        this.kind = kind;
+        this.size = size;
+        this.created = created;
+        this.modified = modified;
+        this.cid = cid;
+    }
+
+    public String getName() {
+        return this.name;
+    }
+
+    public Kind getKind() {
+        return this.kind;
+    }
+
+    public boolean isDirectory() {
+        return this.kind == Kind.DIRECTORY;
+    }
+
+    /** Content length in bytes for files, number of children for directories. */
+    public long getSize() {
+        return this.size;
+    }
+
+    /** Creation time in seconds since the Unix epoch, or null if not recorded. */
+    @Nullable
+    public Long getCreated() {
+        return this.created;
+    }
+
+    /** Modification time in seconds since the Unix epoch, or null if not recorded. */
+    @Nullable
+    public Long getModified() {
+        return this.modified;
+    }
+
+    @Nullable
+    public String getCid() {
+        return this.cid;
+    }
+
+    static Entry fromJson(@NonNull JSONObject obj) throws JSONException {
+        return new Entry(
+            obj.getString("name"),
+            Kind.parse(obj.getString("kind")),
+            obj.getLong("size"),
+            obj.isNull("created") ? null : obj.getLong("created"),
+            obj.isNull("modified") ? null : obj.getLong("modified"),
+            obj.isNull("cid") ? null : obj.getString("cid")
+        );
+    }
+
+    /** Parses the JSON array returned by {@link Fs#ls}. */
+    @NonNull
+    public static List<Entry> parseList(@NonNull byte[] lsOutput) throws JSONException {
+        JSONArray array = new JSONArray(new String(lsOutput, StandardCharsets.UTF_8));
+        List<Entry> entries = new ArrayList<>(array.length());
+        for (int i = 0; i < array.length(); i++) {
+            entries.add(fromJson(array.getJSONObject(i)));
+        }
+        return entries;
+    }
+}
@@ -3,6 +3,7 @@ package land.fx.wnfslib;
 import android.util.Log;
 
 import androidx.annotation.NonNull;
+import androidx.annotation.Nullable;
 
 import java.nio.charset.StandardCharsets;
 import java.util.Arrays;
@@ -22,26 +23,84 @@ public final class Fs {
 
     private static native ConfigResult writeFileFromPathNative(Datastore datastore, String cid, String path, String filename);
 
-    private static native ConfigResult writeFileStreamFromPathNative(Datastore datastore, String cid, String path, String filename);
+    private static native ConfigResult writeFileStreamFromPathNative(Datastore datastore, String cid, String path, String filename, ProgressListener listener, long intervalMs, CancellationToken token);
 
     private static native ConfigResult writeFileNative(Datastore datastore, String cid, String path, byte[] content);
 
+
+
     private static native BytesResult lsNative(Datastore datastore, String cid, String path);
 
     private static native ConfigResult mkdirNative(Datastore datastore, String cid, String path);
 
+    private static native ConfigResult commitTransactionNative(Datastore datastore, String cid, String[] kinds, String[] paths, String[] targets, byte[][] contents);
+
     private static native ConfigResult rmNative(Datastore datastore, String cid, String path);
 
     private static native ConfigResult mvNative(Datastore datastore, String cid, String sourcePath, String targetPath);
 
     private static native ConfigResult cpNative(Datastore datastore, String cid, String sourcePath, String targetPath);
 
+    private static native BytesResult writeDirFromPathNative(Datastore datastore, String cid, String path, String localDir, boolean skipHidden, boolean overwrite);
+
+    private static native BytesResult readDirToPathNative(Datastore datastore, String cid, String path, String localDir);
+
+    private static native LongResult exportCarNative(Datastore datastore, String cid, String outputPath, String subtreePath);
+
     private static native StringResult readFileToPathNative(Datastore datastore, String cid, String path, String filename);
 
-    private static native StringResult readFilestreamToPathNative(Datastore datastore, String cid, String path, String filename);
+    private static native StringResult readFilestreamToPathNative(Datastore datastore, String cid, String path, String filename, long offset, ProgressListener listener, long intervalMs, CancellationToken token);
     
     private static native BytesResult readFileNative(Datastore datastore, String cid, String path);
 
+    private static native BytesResult readFileRangeNative(Datastore datastore, String cid, String path, long offset, long length);
+
+    private static native BytesResult statNative(Datastore datastore, String cid, String path);
+
+    private static native StringResult existsNative(Datastore datastore, String cid, String path);
+
+    private static native LongResult openSessionNative(Datastore datastore, String cid, byte[] wnfsKey, long cacheBytes, boolean writeBack);
+
+    private static native BytesResult cacheStatsSessionNative(long session);
+
+    private static native Result enableDiskCacheNative(String directory, long maxBytes, byte[] key);
+
+    private static native Result disableDiskCacheNative();
+
+    private static native Result clearCacheNative();
+
+    private static native BytesResult diskCacheStatsNative();
+
+    private static native Result setPutPolicyNative(int policy);
+
+    private static native BytesResult putStatsNative();
+
+    private static native Result enableUploadQueueNative(String directory);
+
+    private static native Result disableUploadQueueNative();
+
+    private static native BytesResult pendingUploadsNative();
+
+    private static native LongResult syncPendingNative(Datastore datastore);
+
+    private static native Result closeSessionNative(long session);
+
+    private static native ConfigResult flushSessionNative(long session);
+
+    private static native ConfigResult mkdirSessionNative(long session, String path);
+
+    private static native ConfigResult writeFileSessionNative(long session, String path, byte[] content);
+
+    private static native BytesResult readFileSessionNative(long session, String path);
+
+    private static native BytesResult lsSessionNative(long session, String path);
+
+    private static native ConfigResult rmSessionNative(long session, String path);
+
+    private static native ConfigResult mvSessionNative(long session, String sourcePath, String targetPath);
+
+    private static native ConfigResult cpSessionNative(long session, String sourcePath, String targetPath);
+
 
 
     @NonNull
@@ -51,9 +110,12 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.init", res.getReason());
+                throw WnfsException.create("Fs.init", res.getCode(), res.getReason());
             }
         }
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -64,9 +126,12 @@ public final class Fs {
         try {
             Result res = loadWithWNFSKeyNative(datastore, wnfsKey, cid);
             if(res == null || !res.ok()) {
-                throw new WnfsException("Fs.loadWithWNFSKey", res.getReason());
+                throw WnfsException.create("Fs.loadWithWNFSKey", res.getCode(), res.getReason());
             }
         }
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -79,9 +144,12 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.writeFileFromPath", res.getReason());
+                throw WnfsException.create("Fs.writeFileFromPath", res.getCode(), res.getReason());
             }
         } 
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -89,14 +157,59 @@ public final class Fs {
 
     @NonNull
     public static Config writeFileStreamFromPath(Datastore datastore, String cid, String path, String filename) throws Exception {
+        return writeFileStreamFromPath(datastore, cid, path, filename, null, 0);
+    }
+
+    /**
+     * Like {@link #writeFileStreamFromPath(Datastore, String, String, String)}, reporting bytes
+     * read from filename to listener at most once every intervalMs and once on completion.
+     */
+    @NonNull
+    public static Config writeFileStreamFromPath(Datastore datastore, String cid, String path, String filename, ProgressListener listener, long intervalMs) throws Exception {
+        return writeFileStreamFromPath(datastore, cid, path, filename, listener, intervalMs, null);
+    }
+
+    /**
+     * Streaming upload that stops with a CancelledException once token is cancelled. The new
+     * root is only committed after the whole file is read, so cancelling leaves cid current.
+     * listener may be null.
+     */
+    @NonNull
+    public static Config writeFileStreamFromPath(Datastore datastore, String cid, String path, String filename, ProgressListener listener, long intervalMs, CancellationToken token) throws Exception {
         try {
-            ConfigResult res = writeFileStreamFromPathNative(datastore, cid, path, filename);
+            ConfigResult res = writeFileStreamFromPathNative(datastore, cid, path, filename, listener, intervalMs, token);
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.writeFileStreamFromPath", res.getReason());
+                throw WnfsException.create("Fs.writeFileStreamFromPath", res.getCode(), res.getReason());
             }
         } 
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Uploads localDir and everything below it into path, creating directories as needed, and
+     * stores the forest once at the end. Files that fail are listed in the report rather than
+     * aborting the upload; the new root is in {@link TransferReport#getConfig()}.
+     */
+    @NonNull
+    public static TransferReport writeDirFromPath(Datastore datastore, String cid, String path, String localDir, UploadOptions options) throws Exception {
+        try {
+            BytesResult res = writeDirFromPathNative(datastore, cid, path, localDir, options.getSkipHidden(), options.getOverwrite());
+            if(res != null && res.ok()) {
+                return TransferReport.parse(res.getResult());
+            } else {
+                throw WnfsException.create("Fs.writeDirFromPath", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -109,9 +222,12 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.writeFile", res.getReason());
+                throw WnfsException.create("Fs.writeFile", res.getCode(), res.getReason());
             }
         } 
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -127,36 +243,57 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.ls", res.getReason());
+                throw WnfsException.create("Fs.ls", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static List<Entry> lsEntries(Datastore datastore, String cid, String path) throws Exception {
+        return Entry.parseList(ls(datastore, cid, path));
+    }
+
+    @NonNull
+    public static Stat stat(Datastore datastore, String cid, String path) throws Exception {
+        try {
+            BytesResult res = statNative(datastore, cid, path);
+            if(res != null && res.ok()) {
+                return Stat.parse(res.getResult());
+            } else {
+                throw WnfsException.create("Fs.stat", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Returns the kind of node at path, or null if nothing exists there.
+     * File content is not read.
+     */
+    @Nullable
+    public static Entry.Kind exists(Datastore datastore, String cid, String path) throws Exception {
+        try {
+            StringResult res = existsNative(datastore, cid, path);
+            if(res != null && res.ok()) {
+                String kind = res.getResult();
+                return "none".equals(kind) ? null : Entry.Kind.parse(kind);
+            } else {
+                throw WnfsException.create("Fs.exists", res.getCode(), res.getReason());
             }
-            /*JSONArray output = new JSONArray();
-            byte[] rowSeparatorPattern = {33, 33, 33}; //!!!
-            byte[] itemSeparatorPattern = {63, 63, 63}; //???
-            List<byte[]> rows = split(rowSeparatorPattern, lsResult);
-            for (byte[] element : rows) {
-                JSONObject obj = new JSONObject();
-                List<byte[]> rowDetails = split(itemSeparatorPattern, element);
-                if (!rowDetails.isEmpty()) {
-                    String name = new String(rowDetails.get(0), StandardCharsets.UTF_8);
-                    if(!name.isEmpty()) {
-                        obj.put("name", name);
-                        if(rowDetails.size() >= 2) {
-                            String creation = new String(rowDetails.get(1), StandardCharsets.UTF_8);
-                            obj.put("creation", creation);
-                        } else {
-                            obj.put("creation", "");
-                        }
-                        if(rowDetails.size() >= 3) {
-                            String modification = new String(rowDetails.get(2), StandardCharsets.UTF_8);
-                            obj.put("modification", modification);
-                        } else {
-                            obj.put("modification", "");
-                        }
-                        output.put(obj);
-                    }
-                }
-                
-            }*/
+        }
+        catch(WnfsException e) {
+            throw e;
         }
         catch(Exception e) {
             throw new Exception(e.getMessage());
@@ -170,9 +307,36 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.mkdir", res.getReason());
+                throw WnfsException.create("Fs.mkdir", res.getCode(), res.getReason());
             }
         } 
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Starts a transaction on the given root; see {@link Transaction}. */
+    @NonNull
+    public static Transaction begin(Datastore datastore, String cid) {
+        return new Transaction(datastore, cid);
+    }
+
+    @NonNull
+    static Config commitTransaction(Datastore datastore, String cid, String[] kinds, String[] paths, String[] targets, byte[][] contents) throws Exception {
+        try {
+            ConfigResult res = commitTransactionNative(datastore, cid, kinds, paths, targets, contents);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.commitTransaction", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -185,9 +349,12 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.rm", res.getReason());
+                throw WnfsException.create("Fs.rm", res.getCode(), res.getReason());
             }
         } 
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -200,9 +367,12 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.mv", res.getReason());
+                throw WnfsException.create("Fs.mv", res.getCode(), res.getReason());
             }
         } 
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -215,9 +385,12 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.cp", res.getReason());
+                throw WnfsException.create("Fs.cp", res.getCode(), res.getReason());
             }
         } 
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -230,9 +403,66 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.readFileToPathNative", res.getReason());
+                throw WnfsException.create("Fs.readFileToPathNative", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Recreates the directory at path and everything below it inside localDir, setting each
+     * local mtime from the node's modified time. Only the modified time is preserved; the
+     * node's created time is not applied, as Android cannot set a file's creation time.
+     * Entries that cannot be read, for example because their blocks are missing from the
+     * datastore, are listed in the report's failures.
+     */
+    @NonNull
+    public static TransferReport readDirToPath(Datastore datastore, String cid, String path, String localDir) throws Exception {
+        try {
+            BytesResult res = readDirToPathNative(datastore, cid, path, localDir);
+            if(res != null && res.ok()) {
+                return TransferReport.parse(res.getResult());
+            } else {
+                throw WnfsException.create("Fs.readDirToPath", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Writes every block of the forest at cid into a CAR file, see {@link #exportCar(Datastore, String, String, String)}. */
+    public static long exportCar(Datastore datastore, String cid, String outputPath) throws Exception {
+        return exportCar(datastore, cid, outputPath, null);
+    }
+
+    /**
+     * Writes the forest at cid into a CARv1 file at outputPath with cid as its
+     * root, for backups or for handing the forest to another device without a
+     * Datastore. Without subtreePath every block reachable from cid is written;
+     * with it only the blocks needed to open the forest and read everything
+     * under that path. The blocks stay encrypted. Returns how many were written.
+     */
+    public static long exportCar(Datastore datastore, String cid, String outputPath, @Nullable String subtreePath) throws Exception {
+        try {
+            LongResult res = exportCarNative(datastore, cid, outputPath, subtreePath);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.exportCar", res.getCode(), res.getReason());
             }
         }
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -240,14 +470,69 @@ public final class Fs {
 
     @NonNull
     public static String readFilestreamToPath(Datastore datastore, String cid, String path, String filename) throws Exception {
+        return readFilestreamToPath(datastore, cid, path, filename, 0);
+    }
+
+    /**
+     * Streams the file content from offset onwards into the local file, writing at the same
+     * position and discarding whatever followed offset. Use the local file's length to resume
+     * an interrupted download. An offset past the end of the file or of the local file is
+     * rejected.
+     */
+    @NonNull
+    public static String readFilestreamToPath(Datastore datastore, String cid, String path, String filename, long offset) throws Exception {
+        return readFilestreamToPath(datastore, cid, path, filename, offset, null, 0);
+    }
+
+    /**
+     * Like {@link #readFilestreamToPath(Datastore, String, String, String, long)}, reporting the
+     * position in the file to listener at most once every intervalMs and once on completion.
+     */
+    @NonNull
+    public static String readFilestreamToPath(Datastore datastore, String cid, String path, String filename, long offset, ProgressListener listener, long intervalMs) throws Exception {
+        return readFilestreamToPath(datastore, cid, path, filename, offset, listener, intervalMs, null);
+    }
+
+    /**
+     * Streaming download that stops with a CancelledException once token is cancelled. The bytes
+     * written so far are kept, so the download can be resumed from the local file's length.
+     * listener may be null.
+     */
+    @NonNull
+    public static String readFilestreamToPath(Datastore datastore, String cid, String path, String filename, long offset, ProgressListener listener, long intervalMs, CancellationToken token) throws Exception {
         try{
-            StringResult res = readFilestreamToPathNative(datastore, cid, path, filename);
+            StringResult res = readFilestreamToPathNative(datastore, cid, path, filename, offset, listener, intervalMs, token);
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.readFilestreamToPathNative", res.getReason());
+                throw WnfsException.create("Fs.readFilestreamToPathNative", res.getCode(), res.getReason());
             }
         }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Reads at most length bytes starting at offset. Only the blocks covering the range are
+     * fetched from the datastore; the result is shorter than length at the end of the file.
+     */
+    @NonNull
+    public static byte[] readFileRange(Datastore datastore, String cid, String path, long offset, long length) throws Exception {
+        try{
+            BytesResult res = readFileRangeNative(datastore, cid, path, offset, length);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.readFileRangeNative", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -259,9 +544,436 @@ public final class Fs {
             if(res != null && res.ok()) {
                 return res.getResult();
             } else {
-                throw new WnfsException("Fs.readFileNative", res.getReason());
+                throw WnfsException.create("Fs.readFileNative", res.getCode(), res.getReason());
             }
         }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Loads the forest once and keeps it in native memory. The returned handle
+     * can be passed to the session variants of mkdir/ls/readFile/writeFile/rm/mv/cp
+     * and must be released with {@link #close(long)}.
+     */
+    public static long openSession(Datastore datastore, String cid, byte[] wnfsKey) throws Exception {
+        return openSession(datastore, cid, wnfsKey, 0);
+    }
+
+    /**
+     * Like {@link #openSession(Datastore, String, byte[])}, but keeps up to
+     * cacheBytes of recently used blocks in native memory so that reading them
+     * again does not call the Datastore. The cache is dropped on {@link #close(long)}.
+     */
+    public static long openSession(Datastore datastore, String cid, byte[] wnfsKey, long cacheBytes) throws Exception {
+        return openSession(datastore, cid, wnfsKey, cacheBytes, false);
+    }
+
+    /**
+     * Like {@link #openSession(Datastore, String, byte[], long)}. With writeBack,
+     * blocks written through the session are kept in native memory instead of
+     * being put, and the CIDs the session returns can only be read through it.
+     * {@link #flush(long)} then sends just the blocks the final root refers to,
+     * skipping the ones intermediate edits superseded. Blocks not flushed before
+     * {@link #close(long)} are lost.
+     */
+    public static long openSession(Datastore datastore, String cid, byte[] wnfsKey, long cacheBytes, boolean writeBack) throws Exception {
+        try {
+            LongResult res = openSessionNative(datastore, cid, wnfsKey, cacheBytes, writeBack);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.openSession", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static CacheStats getCacheStats(long session) throws Exception {
+        try {
+            BytesResult res = cacheStatsSessionNative(session);
+            if(res != null && res.ok()) {
+                return CacheStats.parse(res.getResult());
+            } else {
+                throw WnfsException.create("Fs.getCacheStats", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Commits the session's current state and puts the blocks it still holds
+     * back. The returned CID can be loaded from the Datastore alone.
+     */
+    @NonNull
+    public static Config flush(long session) throws Exception {
+        try {
+            ConfigResult res = flushSessionNative(session);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.flush", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    public static void close(long session) throws Exception {
+        try {
+            Result res = closeSessionNative(session);
+            if(res == null || !res.ok()) {
+                throw WnfsException.create("Fs.close", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static Config mkdir(long session, String path) throws Exception {
+        try {
+            ConfigResult res = mkdirSessionNative(session, path);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.mkdir", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static Config writeFile(long session, String path, byte[] content) throws Exception {
+        try {
+            ConfigResult res = writeFileSessionNative(session, path, content);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.writeFile", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    public static byte[] readFile(long session, String path) throws Exception {
+        try {
+            BytesResult res = readFileSessionNative(session, path);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.readFile", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static byte[] ls(long session, String path) throws Exception {
+        try {
+            BytesResult res = lsSessionNative(session, path);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.ls", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static List<Entry> lsEntries(long session, String path) throws Exception {
+        return Entry.parseList(ls(session, path));
+    }
+
+    @NonNull
+    public static Config rm(long session, String path) throws Exception {
+        try {
+            ConfigResult res = rmSessionNative(session, path);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.rm", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static Config mv(long session, String sourcePath, String targetPath) throws Exception {
+        try {
+            ConfigResult res = mvSessionNative(session, sourcePath, targetPath);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.mv", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    @NonNull
+    public static Config cp(long session, String sourcePath, String targetPath) throws Exception {
+        try {
+            ConfigResult res = cpSessionNative(session, sourcePath, targetPath);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.cp", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Keeps blocks read from or written to any Datastore in an encrypted store
+     * under directory, up to maxBytes, evicting the least recently used first.
+     * It survives restarts, so recently used folders can be listed and opened
+     * again without Datastore calls. key encrypts the cache; pass the same key
+     * after a restart or the cached blocks are ignored. Replaces the cache
+     * enabled before; sessions already open keep the one they started with.
+     */
+    public static void enableDiskCache(@NonNull String directory, long maxBytes, @NonNull byte[] key) throws Exception {
+        try {
+            Result res = enableDiskCacheNative(directory, maxBytes, key);
+            if(res == null || !res.ok()) {
+                throw WnfsException.create("Fs.enableDiskCache", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Stops using the persistent cache. Its files stay on disk. */
+    public static void disableDiskCache() throws Exception {
+        try {
+            Result res = disableDiskCacheNative();
+            if(res == null || !res.ok()) {
+                throw WnfsException.create("Fs.disableDiskCache", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Removes every block from the persistent cache. Does nothing if none is enabled. */
+    public static void clearCache() throws Exception {
+        try {
+            Result res = clearCacheNative();
+            if(res == null || !res.ok()) {
+                throw WnfsException.create("Fs.clearCache", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Counters of the persistent cache since it was enabled; all zero if none is. */
+    @NonNull
+    public static CacheStats getDiskCacheStats() throws Exception {
+        try {
+            BytesResult res = diskCacheStatsNative();
+            if(res != null && res.ok()) {
+                return CacheStats.parse(res.getResult());
+            } else {
+                throw WnfsException.create("Fs.getDiskCacheStats", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Sets when blocks are sent to the Datastore by every later call. The default,
+     * {@link PutPolicy#ALWAYS}, puts every block; the others skip blocks that are
+     * already stored, which saves uploads when a file shares content with an
+     * earlier version.
+     */
+    public static void setPutPolicy(@NonNull PutPolicy policy) throws Exception {
+        try {
+            Result res = setPutPolicyNative(policy.getValue());
+            if(res == null || !res.ok()) {
+                throw WnfsException.create("Fs.setPutPolicy", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Blocks put and skipped since the library was loaded. */
+    @NonNull
+    public static PutStats getPutStats() throws Exception {
+        try {
+            BytesResult res = putStatsNative();
+            if(res != null && res.ok()) {
+                return PutStats.parse(res.getResult());
+            } else {
+                throw WnfsException.create("Fs.getPutStats", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Lets every later call keep working while the Datastore is unreachable.
+     * Blocks the Datastore fails to put are kept in a queue under directory
+     * instead, and so is every block written after them until the queue is
+     * drained with {@link #syncPending}; the call returns its new root CID as
+     * usual. Blocks still queued from an earlier run are picked up again.
+     * Replaces the queue enabled before; sessions already open keep the one
+     * they started with.
+     */
+    public static void enableUploadQueue(@NonNull String directory) throws Exception {
+        try {
+            Result res = enableUploadQueueNative(directory);
+            if(res == null || !res.ok()) {
+                throw WnfsException.create("Fs.enableUploadQueue", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Stops queueing failed puts. Blocks already queued stay on disk for the next {@link #enableUploadQueue}. */
+    public static void disableUploadQueue() throws Exception {
+        try {
+            Result res = disableUploadQueueNative();
+            if(res == null || !res.ok()) {
+                throw WnfsException.create("Fs.disableUploadQueue", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /** Blocks waiting in the upload queue; none if no queue is enabled. */
+    @NonNull
+    public static PendingUploads pendingUploads() throws Exception {
+        try {
+            BytesResult res = pendingUploadsNative();
+            if(res != null && res.ok()) {
+                return PendingUploads.parse(res.getResult());
+            } else {
+                throw WnfsException.create("Fs.pendingUploads", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
+        catch(Exception e) {
+            throw new Exception(e.getMessage());
+        }
+    }
+
+    /**
+     * Puts the queued blocks into datastore, each after the blocks it links
+     * to, so the Datastore never holds a root whose children are missing.
+     * Returns how many blocks were sent. If a put fails the blocks sent so far
+     * leave the queue and the rest stay for the next call.
+     */
+    public static long syncPending(@NonNull Datastore datastore) throws Exception {
+        try {
+            LongResult res = syncPendingNative(datastore);
+            if(res != null && res.ok()) {
+                return res.getResult();
+            } else {
+                throw WnfsException.create("Fs.syncPending", res.getCode(), res.getReason());
+            }
+        }
+        catch(WnfsException e) {
+            throw e;
+        }
         catch(Exception e) {
             throw new Exception(e.getMessage());
         }
@@ -0,0 +1,13 @@
+package land.fx.wnfslib;
+
+/**
+ * A Datastore that can tell whether it already holds a block. Under
+ * {@link PutPolicy#CHECK_FIRST} and {@link PutPolicy#TRUST_CACHE} blocks it
+ * reports as held are not put again.
+ */
+public interface IndexedDatastore extends Datastore {
+    boolean has(byte[] cid);
+
+    /** Answers {@link #has} for each CID, in the same order. Blocks are checked a batch at a time. */
+    boolean[] hasMany(byte[][] cids);
+}
@@ -0,0 +1,37 @@
+package land.fx.wnfslib;
+
+import androidx.annotation.NonNull;
+
+import java.nio.charset.StandardCharsets;
+import org.json.JSONException;
+import org.json.JSONObject;
+
+/** Blocks waiting in the upload queue, see {@link Fs#pendingUploads}. */
+public final class PendingUploads {
+    private final long blocks;
+    private final long bytes;
+
+    public PendingUploads(long blocks, long bytes) {
+        super();
+        this.blocks = blocks;
+        this.bytes = bytes;
+    }
+
+    public long getBlocks() {
+        return this.blocks;
+    }
+
+    public long getBytes() {
+        return this.bytes;
+    }
+
+    /** Parses the JSON object returned by {@link Fs#pendingUploads}. */
+    @NonNull
+    public static PendingUploads parse(@NonNull byte[] pendingOutput) throws JSONException {
+        JSONObject obj = new JSONObject(new String(pendingOutput, StandardCharsets.UTF_8));
+        return new PendingUploads(
+            obj.getLong("blocks"),
+            obj.getLong("bytes")
+        );
+    }
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib;
+
+/**
+ * Receives progress of a streaming upload or download. Called on the thread running the
+ * transfer, so implementations should return quickly and post to the UI thread themselves.
+ */
+public interface ProgressListener {
+    void onProgress(long processedBytes, long totalBytes);
+}
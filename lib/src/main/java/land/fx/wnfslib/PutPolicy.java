@@ -0,0 +1,24 @@
+package land.fx.wnfslib;
+
+/**
+ * When a block is sent to the Datastore, see {@link Fs#setPutPolicy}. Keep in sync
+ * with `PutPolicy` in wnfslib/src/dedup.rs.
+ */
+public enum PutPolicy {
+    /** Every block is put. */
+    ALWAYS(0),
+    /** Blocks an {@link IndexedDatastore} says it has are skipped. */
+    CHECK_FIRST(1),
+    /** Blocks in the persistent cache are assumed stored and skipped; the rest are checked as with CHECK_FIRST. */
+    TRUST_CACHE(2);
+
+    private final int value;
+
+    PutPolicy(int value) {
+        this.value = value;
+    }
+
+    public int getValue() {
+        return this.value;
+    }
+}
@@ -0,0 +1,61 @@
+package land.fx.wnfslib;
+
+import androidx.annotation.NonNull;
+
+import java.nio.charset.StandardCharsets;
+import org.json.JSONException;
+import org.json.JSONObject;
+
+/** Counters of blocks put and skipped since the library was loaded, see {@link Fs#getPutStats}. */
+public final class PutStats {
+    private final long puts;
+    private final long putBytes;
+    private final long checks;
+    private final long skipped;
+    private final long skippedBytes;
+
+    public PutStats(long puts, long putBytes, long checks, long skipped, long skippedBytes) {
+        super();
+        this.puts = puts;
+        this.putBytes = putBytes;
+        this.checks = checks;
+        this.skipped = skipped;
+        this.skippedBytes = skippedBytes;
+    }
+
+    /** Blocks sent to a Datastore. */
+    public long getPuts() {
+        return this.puts;
+    }
+
+    public long getPutBytes() {
+        return this.putBytes;
+    }
+
+    /** Calls to {@link IndexedDatastore#has}. */
+    public long getChecks() {
+        return this.checks;
+    }
+
+    /** Blocks not sent because they were already stored. */
+    public long getSkipped() {
+        return this.skipped;
+    }
+
+    public long getSkippedBytes() {
+        return this.skippedBytes;
+    }
+
+    /** Parses the JSON object returned by {@link Fs#getPutStats}. */
+    @NonNull
+    public static PutStats parse(@NonNull byte[] statsOutput) throws JSONException {
+        JSONObject obj = new JSONObject(new String(statsOutput, StandardCharsets.UTF_8));
+        return new PutStats(
+            obj.getLong("puts"),
+            obj.getLong("putBytes"),
+            obj.getLong("checks"),
+            obj.getLong("skipped"),
+            obj.getLong("skippedBytes")
+        );
+    }
+}
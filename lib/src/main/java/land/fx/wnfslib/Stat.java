@@ -0,0 +1,79 @@
+package land.fx.wnfslib;
+
+import androidx.annotation.NonNull;
+import androidx.annotation.Nullable;
+
+import java.nio.charset.StandardCharsets;
+import org.json.JSONException;
+import org.json.JSONObject;
+
+public final class Stat {
+    private final Entry.Kind kind;
+    private final long size;
+    private final Long created;
+    private final Long modified;
+    private final String cid;
+    private final JSONObject metadata;
+
+    public Stat(Entry.Kind kind, long size, Long created, Long modified, String cid, JSONObject metadata) {
+        super();
+        this.kind = kind;
+        this.size = size;
+        this.created = created;
+        this.modified = modified;
+        this.cid = cid;
+        this.metadata = metadata;
+    }
+
+    public Entry.Kind getKind() {
+        return this.kind;
+    }
+
+    public boolean isDirectory() {
+        return this.kind == Entry.Kind.DIRECTORY;
+    }
+
+    /** Content length in bytes for files, number of children for directories. */
+    public long getSize() {
+        return this.size;
+    }
+
+    /** Creation time in seconds since the Unix epoch, or null if not recorded. */
+    @Nullable
+    public Long getCreated() {
+        return this.created;
+    }
+
+    /** Modification time in seconds since the Unix epoch, or null if not recorded. */
+    @Nullable
+    public Long getModified() {
+        return this.modified;
+    }
+
+    /** CID of the encrypted block holding this revision of the node, or null if it has changed since it was last stored. */
+    @Nullable
+    public String getCid() {
+        return this.cid;
+    }
+
+    /** User metadata stored on the node, excluding the created/modified timestamps. */
+    @NonNull
+    public JSONObject getMetadata() {
+        return this.metadata;
+    }
+
+    /** Parses the JSON object returned by {@link Fs#stat}. */
+    @NonNull
+    public static Stat parse(@NonNull byte[] statOutput) throws JSONException {
+        JSONObject obj = new JSONObject(new String(statOutput, StandardCharsets.UTF_8));
+        JSONObject metadata = obj.optJSONObject("metadata");
+        return new Stat(
+            Entry.Kind.parse(obj.getString("kind")),
+            obj.getLong("size"),
+            obj.isNull("created") ? null : obj.getLong("created"),
+            obj.isNull("modified") ? null : obj.getLong("modified"),
+            obj.isNull("cid") ? null : obj.getString("cid"),
+            metadata != null ? metadata : new JSONObject()
+        );
+    }
+}
@@ -0,0 +1,77 @@
+package land.fx.wnfslib;
+
+import androidx.annotation.NonNull;
+
+import java.util.ArrayList;
+import java.util.List;
+
+/**
+ * Queues mutations against one root and applies them with a single commit. Nothing touches the
+ * forest until {@link #commit()}; if any operation fails there, none of them are committed and
+ * the starting root remains valid. Obtain one with {@link Fs#begin(Datastore, String)}.
+ */
+public final class Transaction {
+    private final Datastore datastore;
+    private final String cid;
+    private final List<String> kinds = new ArrayList<>();
+    private final List<String> paths = new ArrayList<>();
+    private final List<String> targets = new ArrayList<>();
+    private final List<byte[]> contents = new ArrayList<>();
+
+    Transaction(Datastore datastore, String cid) {
+        super();
+        this.datastore = datastore;
+        this.cid = cid;
+    }
+
+    @NonNull
+    public Transaction mkdir(String path) {
+        return queue("mkdir", path, null, null);
+    }
+
+    @NonNull
+    public Transaction writeFile(String path, byte[] content) {
+        return queue("writeFile", path, null, content);
+    }
+
+    @NonNull
+    public Transaction rm(String path) {
+        return queue("rm", path, null, null);
+    }
+
+    @NonNull
+    public Transaction mv(String sourcePath, String targetPath) {
+        return queue("mv", sourcePath, targetPath, null);
+    }
+
+    @NonNull
+    public Transaction cp(String sourcePath, String targetPath) {
+        return queue("cp", sourcePath, targetPath, null);
+    }
+
+    /** Number of queued operations. */
+    public int size() {
+        return this.kinds.size();
+    }
+
+    /** Applies the queued operations in order and returns the new root. */
+    @NonNull
+    public Config commit() throws Exception {
+        return Fs.commitTransaction(
+            this.datastore,
+            this.cid,
+            this.kinds.toArray(new String[0]),
+            this.paths.toArray(new String[0]),
+            this.targets.toArray(new String[0]),
+            this.contents.toArray(new byte[0][])
+        );
+    }
+
+    private Transaction queue(String kind, String path, String target, byte[] content) {
+        this.kinds.add(kind);
+        this.paths.add(path);
+        this.targets.add(target);
+        this.contents.add(content);
+        return this;
+    }
+}
@@ -0,0 +1,103 @@
+package land.fx.wnfslib;
+
+import androidx.annotation.NonNull;
+import androidx.annotation.Nullable;
+
+import java.nio.charset.StandardCharsets;
+import java.util.ArrayList;
+import java.util.List;
+import org.json.JSONArray;
+import org.json.JSONException;
+import org.json.JSONObject;
+import land.fx.wnfslib.exceptions.ErrorCode;
+
+/** Outcome of a directory upload or download. Paths are relative to the transferred directory. */
+public final class TransferReport {
+    public static final class Failure {
+        private final String path;
+        private final String reason;
+        private final ErrorCode code;
+
+        public Failure(String path, String reason, ErrorCode code) {
+            super();
+            this.path = path;
+            this.reason = reason;
+            this.code = code;
+        }
+
+        public String getPath() {
+            return this.path;
+        }
+
+        public String getReason() {
+            return this.reason;
+        }
+
+        public ErrorCode getCode() {
+            return this.code;
+        }
+    }
+
+    private final Config config;
+    private final List<String> succeeded;
+    private final List<String> skipped;
+    private final List<Failure> failed;
+
+    public TransferReport(Config config, List<String> succeeded, List<String> skipped, List<Failure> failed) {
+        super();
+        this.config = config;
+        this.succeeded = succeeded;
+        this.skipped = skipped;
+        this.failed = failed;
+    }
+
+    /** The new root after an upload; null for downloads, which leave the forest unchanged. */
+    @Nullable
+    public Config getConfig() {
+        return this.config;
+    }
+
+    @NonNull
+    public List<String> getSucceeded() {
+        return this.succeeded;
+    }
+
+    @NonNull
+    public List<String> getSkipped() {
+        return this.skipped;
+    }
+
+    @NonNull
+    public List<Failure> getFailed() {
+        return this.failed;
+    }
+
+    @NonNull
+    public static TransferReport parse(@NonNull byte[] output) throws JSONException {
+        JSONObject obj = new JSONObject(new String(output, StandardCharsets.UTF_8));
+        List<Failure> failed = new ArrayList<>();
+        JSONArray failures = obj.getJSONArray("failed");
+        for (int i = 0; i < failures.length(); i++) {
+            JSONObject failure = failures.getJSONObject(i);
+            failed.add(new Failure(
+                failure.getString("path"),
+                failure.getString("error"),
+                ErrorCode.fromValue(failure.getInt("code"))
+            ));
+        }
+        return new TransferReport(
+            obj.isNull("cid") ? null : new Config(obj.getString("cid")),
+            strings(obj.getJSONArray("succeeded")),
+            strings(obj.getJSONArray("skipped")),
+            failed
+        );
+    }
+
+    private static List<String> strings(JSONArray array) throws JSONException {
+        List<String> result = new ArrayList<>(array.length());
+        for (int i = 0; i < array.length(); i++) {
+            result.add(array.getString(i));
+        }
+        return result;
+    }
+}
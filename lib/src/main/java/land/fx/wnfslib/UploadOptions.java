@@ -0,0 +1,27 @@
+package land.fx.wnfslib;
+
+public final class UploadOptions {
+    private final boolean skipHidden;
+    private final boolean overwrite;
+
+    /** Uploads hidden files and skips files that already exist in the forest. */
+    public UploadOptions() {
+        this(false, false);
+    }
+
+    public UploadOptions(boolean skipHidden, boolean overwrite) {
+        super();
+        this.skipHidden = skipHidden;
+        this.overwrite = overwrite;
+    }
+
+    /** Whether files and directories whose name starts with a dot are left out. */
+    public boolean getSkipHidden() {
+        return this.skipHidden;
+    }
+
+    /** Whether files that already exist in the forest are replaced rather than skipped. */
+    public boolean getOverwrite() {
+        return this.overwrite;
+    }
+}
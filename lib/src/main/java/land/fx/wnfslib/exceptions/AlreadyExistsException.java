@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class AlreadyExistsException extends WnfsException
+{
+    public AlreadyExistsException(String func, String reason)
+    {
+        super(func, ErrorCode.ALREADY_EXISTS, reason);
+    }
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class CancelledException extends WnfsException
+{
+    public CancelledException(String func, String reason)
+    {
+        super(func, ErrorCode.CANCELLED, reason);
+    }
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class CorruptedException extends WnfsException
+{
+    public CorruptedException(String func, String reason)
+    {
+        super(func, ErrorCode.CORRUPTED, reason);
+    }
+}
@@ -0,0 +1,38 @@
+package land.fx.wnfslib.exceptions;
+
+/**
+ * Error codes reported by the native library. Keep in sync with `ErrorCode` in wnfslib/src/core.rs.
+ */
+public enum ErrorCode {
+    UNKNOWN(0),
+    NOT_FOUND(1),
+    ALREADY_EXISTS(2),
+    NOT_A_DIRECTORY(3),
+    NOT_A_FILE(4),
+    INVALID_PATH(5),
+    INVALID_CID(6),
+    WRONG_KEY(7),
+    STORE_UNAVAILABLE(8),
+    STORE_MISMATCH(9),
+    CORRUPTED(10),
+    CANCELLED(11);
+
+    private final int value;
+
+    ErrorCode(int value) {
+        this.value = value;
+    }
+
+    public int getValue() {
+        return this.value;
+    }
+
+    public static ErrorCode fromValue(int value) {
+        for (ErrorCode code : values()) {
+            if (code.value == value) {
+                return code;
+            }
+        }
+        return UNKNOWN;
+    }
+}
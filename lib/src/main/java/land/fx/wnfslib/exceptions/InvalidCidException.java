@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class InvalidCidException extends WnfsException
+{
+    public InvalidCidException(String func, String reason)
+    {
+        super(func, ErrorCode.INVALID_CID, reason);
+    }
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class InvalidPathException extends WnfsException
+{
+    public InvalidPathException(String func, String reason)
+    {
+        super(func, ErrorCode.INVALID_PATH, reason);
+    }
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class NotADirectoryException extends WnfsException
+{
+    public NotADirectoryException(String func, String reason)
+    {
+        super(func, ErrorCode.NOT_A_DIRECTORY, reason);
+    }
+}
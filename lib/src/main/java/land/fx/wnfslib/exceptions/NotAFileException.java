@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class NotAFileException extends WnfsException
+{
+    public NotAFileException(String func, String reason)
+    {
+        super(func, ErrorCode.NOT_A_FILE, reason);
+    }
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class NotFoundException extends WnfsException
+{
+    public NotFoundException(String func, String reason)
+    {
+        super(func, ErrorCode.NOT_FOUND, reason);
+    }
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class StoreMismatchException extends WnfsException
+{
+    public StoreMismatchException(String func, String reason)
+    {
+        super(func, ErrorCode.STORE_MISMATCH, reason);
+    }
+}
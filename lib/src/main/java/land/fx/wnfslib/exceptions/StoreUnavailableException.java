@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class StoreUnavailableException extends WnfsException
+{
+    public StoreUnavailableException(String func, String reason)
+    {
+        super(func, ErrorCode.STORE_UNAVAILABLE, reason);
+    }
+}
@@ -3,14 +3,58 @@ package land.fx.wnfslib.exceptions;
 import java.lang.String;
 import java.lang.Exception;
 
-public final class WnfsException extends Exception
+public class WnfsException extends Exception
 {
+    private final ErrorCode code;
 
-    public WnfsException() {}
+    public WnfsException() {
+        this.code = ErrorCode.UNKNOWN;
+    }
 
     // Constructor that accepts a message
     public WnfsException(String func ,String reason)
+    {
+        this(func, ErrorCode.UNKNOWN, reason);
+    }
+
+    public WnfsException(String func, ErrorCode code, String reason)
     {
         super(String.format("An Error Occured in Fs.%s: %s", func, reason));
+        this.code = code;
+    }
+
+    public ErrorCode getCode() {
+        return this.code;
+    }
+
+    /** Builds the exception subclass matching the numeric code carried in a result object. */
+    public static WnfsException create(String func, int code, String reason) {
+        ErrorCode errorCode = ErrorCode.fromValue(code);
+        switch (errorCode) {
+            case NOT_FOUND:
+                return new NotFoundException(func, reason);
+            case ALREADY_EXISTS:
+                return new AlreadyExistsException(func, reason);
+            case NOT_A_DIRECTORY:
+                return new NotADirectoryException(func, reason);
+            case NOT_A_FILE:
+                return new NotAFileException(func, reason);
+            case INVALID_PATH:
+                return new InvalidPathException(func, reason);
+            case INVALID_CID:
+                return new InvalidCidException(func, reason);
+            case WRONG_KEY:
+                return new WrongKeyException(func, reason);
+            case STORE_UNAVAILABLE:
+                return new StoreUnavailableException(func, reason);
+            case STORE_MISMATCH:
+                return new StoreMismatchException(func, reason);
+            case CORRUPTED:
+                return new CorruptedException(func, reason);
+            case CANCELLED:
+                return new CancelledException(func, reason);
+            default:
+                return new WnfsException(func, errorCode, reason);
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,9 @@
+package land.fx.wnfslib.exceptions;
+
+public final class WrongKeyException extends WnfsException
+{
+    public WrongKeyException(String func, String reason)
+    {
+        super(func, ErrorCode.WRONG_KEY, reason);
+    }
+}
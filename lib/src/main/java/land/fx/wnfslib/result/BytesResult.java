@@ -9,7 +9,15 @@ public final class BytesResult extends TypedResult<byte[]> {
         super(error, result);
     }
 
+    public BytesResult(int code, String error, byte[] result) {
+        super(code, error, result);
+    }
+
     public static BytesResult create(String error, byte[] result ) {
         return new BytesResult(error, result);
     }
+
+    public static BytesResult create(int code, String error, byte[] result) {
+        return new BytesResult(code, error, result);
+    }
 }
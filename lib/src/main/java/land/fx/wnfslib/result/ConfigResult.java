@@ -9,8 +9,16 @@ public final class ConfigResult extends TypedResult<Config> {
         super(error, result);
     }
 
+    public ConfigResult(int code, String error, Config result) {
+        super(code, error, result);
+    }
+
 
     public static ConfigResult create(String error, Config result ) {
         return new ConfigResult(error, result);
     }
+
+    public static ConfigResult create(int code, String error, Config result) {
+        return new ConfigResult(code, error, result);
+    }
 }
@@ -0,0 +1,23 @@
+package land.fx.wnfslib.result;
+
+import land.fx.wnfslib.result.TypedResult;
+import java.lang.Long;
+
+
+public final class LongResult extends TypedResult<Long> {
+    public LongResult(String error, Long result) {
+        super(error, result);
+    }
+
+    public LongResult(int code, String error, Long result) {
+        super(code, error, result);
+    }
+
+    public static LongResult create(String error, Long result ) {
+        return new LongResult(error, result);
+    }
+
+    public static LongResult create(int code, String error, Long result) {
+        return new LongResult(code, error, result);
+    }
+}
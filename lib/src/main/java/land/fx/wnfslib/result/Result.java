@@ -7,8 +7,16 @@ public final class Result extends TypedResult<Object> {
     public Result(String error, Object result) {
         super(error, result);
     }
+
+    public Result(int code, String error, Object result) {
+        super(code, error, result);
+    }
     
     public static Result create(String error, Object result ) {
         return new Result(error, result);
     }
+
+    public static Result create(int code, String error, Object result) {
+        return new Result(code, error, result);
+    }
 }
@@ -9,7 +9,15 @@ public final class StringResult extends TypedResult<String> {
         super(error, result);
     }
 
+    public StringResult(int code, String error, String result) {
+        super(code, error, result);
+    }
+
     public static StringResult create(String error, String result ) {
         return new StringResult(error, result);
     }
+
+    public static StringResult create(int code, String error, String result) {
+        return new StringResult(code, error, result);
+    }
 }
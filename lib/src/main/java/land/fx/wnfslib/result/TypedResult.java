@@ -8,6 +8,7 @@ public abstract class TypedResult<T extends Object> {
     private final T result;
     private final Boolean ok;
     private final String reason;
+    private final int code;
 
 
     public Boolean ok() {
@@ -18,18 +19,29 @@ public abstract class TypedResult<T extends Object> {
         return this.reason;
     }
 
+    /** Numeric error code, see {@link land.fx.wnfslib.exceptions.ErrorCode}. 0 when ok. */
+    public int getCode() {
+        return this.code;
+    }
+
     public T getResult() {
         return this.result;
     }
 
 
     public TypedResult(String error, T result) {
+        this(0, error, result);
+    }
+
+    public TypedResult(int code, String error, T result) {
         if (error == null){
             this.ok = true;
             this.reason = null;
+            this.code = 0;
         } else{
             this.ok = false;
             this.reason = error;
+            this.code = code;
         }
         this.result = result;
     }
@@ -0,0 +1,40 @@
+// Generates wnfs.h for the C ABI in src/ffi.rs into OUT_DIR/include. Set
+// WNFS_HEADER_DIR to also copy it somewhere else, e.g. WNFS_HEADER_DIR=include
+// to refresh the checked-in header.
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=src/core.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=WNFS_HEADER_DIR");
+
+    if let Err(err) = generate() {
+        panic!("generating wnfs.h: {}", err);
+    }
+}
+
+fn generate() -> Result<(), String> {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|err| err.to_string())?;
+    let out_dir = std::env::var("OUT_DIR").map_err(|err| err.to_string())?;
+    let include_dir = Path::new(&out_dir).join("include");
+    std::fs::create_dir_all(&include_dir).map_err(|err| err.to_string())?;
+
+    let config = cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml"))?;
+    let bindings = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(Path::new(&crate_dir).join("src/core.rs"))
+        .with_src(Path::new(&crate_dir).join("src/ffi.rs"))
+        .generate()
+        .map_err(|err| err.to_string())?;
+    bindings.write_to_file(include_dir.join("wnfs.h"));
+    // Lets tests/c_abi.rs find the header.
+    println!("cargo:rustc-env=WNFS_INCLUDE_DIR={}", include_dir.display());
+
+    if let Ok(dir) = std::env::var("WNFS_HEADER_DIR") {
+        let dir = Path::new(&crate_dir).join(dir);
+        std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+        std::fs::copy(include_dir.join("wnfs.h"), dir.join("wnfs.h")).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+#ifndef WNFS_H
+#define WNFS_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Error codes reported by every binding. Exported to C as `WnfsErrorCode`;
+// keep in sync with `land.fx.wnfslib.exceptions.ErrorCode`.
+enum WnfsErrorCode {
+  WNFS_ERROR_CODE_UNKNOWN = 0,
+  WNFS_ERROR_CODE_NOT_FOUND = 1,
+  WNFS_ERROR_CODE_ALREADY_EXISTS = 2,
+  WNFS_ERROR_CODE_NOT_A_DIRECTORY = 3,
+  WNFS_ERROR_CODE_NOT_A_FILE = 4,
+  WNFS_ERROR_CODE_INVALID_PATH = 5,
+  WNFS_ERROR_CODE_INVALID_CID = 6,
+  WNFS_ERROR_CODE_WRONG_KEY = 7,
+  WNFS_ERROR_CODE_STORE_UNAVAILABLE = 8,
+  WNFS_ERROR_CODE_STORE_MISMATCH = 9,
+  WNFS_ERROR_CODE_CORRUPTED = 10,
+  WNFS_ERROR_CODE_CANCELLED = 11,
+};
+typedef int32_t WnfsErrorCode;
+
+// Result of `wnfs_exists`.
+enum WnfsNodeKind {
+  WNFS_NODE_KIND_NONE = 0,
+  WNFS_NODE_KIND_FILE = 1,
+  WNFS_NODE_KIND_DIRECTORY = 2,
+};
+typedef int32_t WnfsNodeKind;
+
+// A private forest loaded from a block store. Every mutating call stores
+// the forest and returns its new root CID, which is what `load` and
+// `reload` take to pick up from there later.
+typedef struct WnfsDrive WnfsDrive;
+
+// Bytes passed across the boundary. Buffers returned by the library must be
+// released with `wnfs_buffer_free`.
+typedef struct WnfsBuffer {
+  uint8_t *data;
+  size_t len;
+} WnfsBuffer;
+
+// Caller-supplied block store, the C counterpart of the `Datastore` used on
+// Android. Callbacks return `true` on success and receive `context` as their
+// first argument.
+typedef struct WnfsBlockStore {
+  void *context;
+  // Fills `out` with the block stored under `cid`. The memory stays owned
+  // by the caller and is handed back through `release_block` once copied.
+  // Returning `true` with `out->data` left null counts as a missing block.
+  bool (*get_block)(void *context, const uint8_t *cid, size_t cid_len, struct WnfsBuffer *out);
+  // Stores `data` under `cid`.
+  bool (*put_block)(void *context,
+                    const uint8_t *cid,
+                    size_t cid_len,
+                    const uint8_t *data,
+                    size_t data_len);
+  // Releases a buffer filled by `get_block`. May be null if nothing needs releasing.
+  void (*release_block)(void *context, struct WnfsBuffer buffer);
+} WnfsBlockStore;
+
+// Creates an empty forest. `out_drive` receives a handle to free with
+// `wnfs_drive_free`; `out_cid`, if not null, the root CID to free with `wnfs_string_free`.
+bool wnfs_init(struct WnfsBlockStore store,
+               const uint8_t *wnfs_key,
+               size_t wnfs_key_len,
+               struct WnfsDrive **out_drive,
+               char **out_cid);
+
+// Opens the forest at `cid` with the key it was created with.
+bool wnfs_load(struct WnfsBlockStore store,
+               const char *cid,
+               const uint8_t *wnfs_key,
+               size_t wnfs_key_len,
+               struct WnfsDrive **out_drive);
+
+void wnfs_drive_free(struct WnfsDrive *drive);
+
+// Lists a directory as the same JSON array `Fs.ls` returns on Android.
+bool wnfs_ls(struct WnfsDrive *drive, const char *path, struct WnfsBuffer *out_json);
+
+// Describes a file or directory as the same JSON object `Fs.stat` returns on Android.
+bool wnfs_stat(struct WnfsDrive *drive, const char *path, struct WnfsBuffer *out_json);
+
+bool wnfs_exists(struct WnfsDrive *drive, const char *path, WnfsNodeKind *out_kind);
+
+bool wnfs_read(struct WnfsDrive *drive, const char *path, struct WnfsBuffer *out_content);
+
+// Writes a file, creating missing parent directories. `out_cid`, if not
+// null, receives the new root CID to free with `wnfs_string_free`.
+bool wnfs_write(struct WnfsDrive *drive,
+                const char *path,
+                const uint8_t *content,
+                size_t content_len,
+                char **out_cid);
+
+bool wnfs_mkdir(struct WnfsDrive *drive, const char *path, char **out_cid);
+
+bool wnfs_rm(struct WnfsDrive *drive, const char *path, char **out_cid);
+
+bool wnfs_mv(struct WnfsDrive *drive, const char *source, const char *target, char **out_cid);
+
+bool wnfs_cp(struct WnfsDrive *drive, const char *source, const char *target, char **out_cid);
+
+void wnfs_buffer_free(struct WnfsBuffer buffer);
+
+void wnfs_string_free(char *text);
+
+// Code of the last failure on this thread, `WNFS_ERROR_CODE_UNKNOWN` if none.
+WnfsErrorCode wnfs_last_error_code(void);
+
+// Message of the last failure on this thread, or null. Owned by the library
+// and valid until the next call on this thread.
+const char *wnfs_last_error_message(void);
+
+#endif /* WNFS_H */
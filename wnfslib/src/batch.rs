@@ -0,0 +1,209 @@
+//! Batching of block store calls. Writing a large file produces thousands of
+//! blocks; `BatchedStore` collects them and hands them to stores that can take
+//! several blocks per call (`BlockBatch`) a batch at a time. In write-back mode
+//! it keeps every block until `flush_reachable`, and then only sends the ones
+//! the final root still refers to.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use anyhow::Result;
+use libipld::cbor::DagCborCodec;
+use libipld::codec::Codec;
+use libipld::{Cid, Ipld};
+use wnfsutils::blockstore::FFIStore;
+
+use crate::dedup::{has_blocks_after, BlockQuery};
+
+/// Blocks collected before a batch is sent, whichever limit is reached first.
+pub const BATCH_BLOCKS: usize = 64;
+pub const BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+/// A block store that can move several blocks per call. The default methods
+/// fall back to one `get_block` or `put_block` per block.
+pub trait BlockBatch<'a>: FFIStore<'a> {
+    /// Fetches the blocks of `cids`, in the same order.
+    fn get_blocks(&self, cids: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        cids.into_iter().map(|cid| self.get_block(cid)).collect()
+    }
+
+    /// Stores `(CID, block)` pairs.
+    fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        blocks.into_iter().try_for_each(|(cid, bytes)| self.put_block(cid, bytes))
+    }
+}
+
+#[derive(Default)]
+struct Pending {
+    blocks: Vec<(Vec<u8>, Vec<u8>)>,
+    /// CID bytes -> index into `blocks`.
+    index: HashMap<Vec<u8>, usize>,
+    size: usize,
+}
+
+/// Holds written blocks until a batch is full or `flush` is called, serving
+/// reads of them from memory meanwhile. Clones share the pending blocks.
+#[derive(Clone)]
+pub struct BatchedStore<S> {
+    inner: S,
+    pending: Rc<RefCell<Pending>>,
+    write_back: bool,
+}
+
+impl<S> BatchedStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner, pending: Rc::default(), write_back: false }
+    }
+
+    /// Keeps every block in memory, however many there are, until
+    /// `flush_reachable` is called.
+    pub fn write_back(inner: S) -> Self {
+        Self { inner, pending: Rc::default(), write_back: true }
+    }
+
+    pub fn is_write_back(&self) -> bool {
+        self.write_back
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a> BatchedStore<S> {
+    /// Sends every pending block to `inner`. On failure the blocks stay
+    /// pending, so a later flush sends them again.
+    pub fn flush(&self) -> Result<(), String> {
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        if pending.blocks.is_empty() {
+            return Ok(());
+        }
+        self.inner.put_blocks(pending.blocks.clone()).map_err(|err| {
+            *self.pending.borrow_mut() = pending;
+            err.to_string()
+        })
+    }
+
+    /// Sends the pending blocks reachable from `root`, children before the
+    /// blocks that link to them, and drops the rest: they belong to states
+    /// that were superseded before `root`. On failure everything stays pending.
+    pub fn flush_reachable(&self, root: &Cid) -> Result<(), String> {
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        let order = reachable(&pending, root);
+        let result = order.chunks(BATCH_BLOCKS).try_for_each(|chunk| {
+            let blocks = chunk.iter().map(|&i| pending.blocks[i].clone()).collect();
+            self.inner.put_blocks(blocks)
+        });
+        result.map_err(|err| {
+            *self.pending.borrow_mut() = pending;
+            err.to_string()
+        })
+    }
+
+    /// Bytes of blocks waiting to be sent.
+    pub fn pending_bytes(&self) -> usize {
+        self.pending.borrow().size
+    }
+}
+
+/// What `WnfsDrive` needs from the `BatchedStore` it was opened over.
+pub(crate) trait Staging {
+    fn is_write_back(&self) -> bool;
+    fn flush(&self) -> Result<(), String>;
+    fn flush_reachable(&self, root: &Cid) -> Result<(), String>;
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a> Staging for BatchedStore<S> {
+    fn is_write_back(&self) -> bool {
+        self.write_back
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        BatchedStore::flush(self)
+    }
+
+    fn flush_reachable(&self, root: &Cid) -> Result<(), String> {
+        BatchedStore::flush_reachable(self, root)
+    }
+}
+
+/// Indices of the pending blocks reachable from `root`, in post-order. Links
+/// are only followed through pending blocks; anything else is already stored.
+fn reachable(pending: &Pending, root: &Cid) -> Vec<usize> {
+    let mut order = Vec::new();
+    let mut expanded = HashSet::new();
+    // (block index, whether its links have already been pushed)
+    let mut stack: Vec<_> = pending.index.get(&root.to_bytes()).map(|&i| (i, false)).into_iter().collect();
+    while let Some((i, done)) = stack.pop() {
+        if done {
+            order.push(i);
+            continue;
+        }
+        if !expanded.insert(i) {
+            continue;
+        }
+        stack.push((i, true));
+        let (cid, bytes) = &pending.blocks[i];
+        for link in links(cid, bytes) {
+            match pending.index.get(&link.to_bytes()) {
+                Some(&child) if !expanded.contains(&child) => stack.push((child, false)),
+                _ => {}
+            }
+        }
+    }
+    order
+}
+
+/// CIDs a DAG-CBOR block links to. Other codecs (the encrypted raw blocks)
+/// link to nothing.
+pub(crate) fn links(cid: &[u8], bytes: &[u8]) -> Vec<Cid> {
+    let mut links = Vec::new();
+    let is_dag_cbor = Cid::try_from(cid).is_ok_and(|cid| cid.codec() == u64::from(DagCborCodec));
+    if is_dag_cbor {
+        if let Ok(ipld) = DagCborCodec.decode::<Ipld>(bytes) {
+            ipld.references(&mut links);
+        }
+    }
+    links
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a> FFIStore<'a> for BatchedStore<S> {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        {
+            let pending = self.pending.borrow();
+            if let Some(&i) = pending.index.get(&cid) {
+                return Ok(pending.blocks[i].1.clone());
+            }
+        }
+        self.inner.get_block(cid)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        let full = {
+            let mut pending = self.pending.borrow_mut();
+            if pending.index.contains_key(&cid) {
+                return Ok(());
+            }
+            let i = pending.blocks.len();
+            pending.size += bytes.len();
+            pending.index.insert(cid.clone(), i);
+            pending.blocks.push((cid, bytes));
+            !self.write_back && (pending.blocks.len() >= BATCH_BLOCKS || pending.size >= BATCH_BYTES)
+        };
+        if full {
+            self.flush().map_err(anyhow::Error::msg)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + BlockQuery<'a> + Clone + 'a> BlockQuery<'a> for BatchedStore<S> {
+    fn has_block(&self, cid: &[u8]) -> Result<Option<bool>> {
+        if self.pending.borrow().index.contains_key(cid) {
+            return Ok(Some(true));
+        }
+        self.inner.has_block(cid)
+    }
+
+    fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+        let pending = self.pending.borrow();
+        has_blocks_after(&self.inner, cids, |cid| pending.index.contains_key(cid))
+    }
+}
@@ -0,0 +1,147 @@
+//! Size-bounded LRU cache of blocks kept in front of a block store, so that
+//! blocks read again within a session (the forest root, HAMT nodes) do not go
+//! back through the `Datastore`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde::Serialize;
+use wnfsutils::blockstore::FFIStore;
+
+pub struct BlockCache {
+    /// Maximum bytes of CIDs plus block data held. 0 disables the cache.
+    capacity: usize,
+    size: usize,
+    tick: u64,
+    /// CID bytes -> (block, tick of last use).
+    blocks: HashMap<Vec<u8>, (Vec<u8>, u64)>,
+    /// Tick of last use -> CID bytes, oldest first.
+    recency: BTreeMap<u64, Vec<u8>>,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub capacity: u64,
+    pub size: u64,
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl BlockCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            size: 0,
+            tick: 0,
+            blocks: HashMap::new(),
+            recency: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn get(&mut self, cid: &[u8]) -> Option<Vec<u8>> {
+        if !self.is_enabled() {
+            return None;
+        }
+        self.tick += 1;
+        let tick = self.tick;
+        match self.blocks.get_mut(cid) {
+            Some((bytes, used)) => {
+                self.recency.remove(used);
+                self.recency.insert(tick, cid.to_vec());
+                *used = tick;
+                self.hits += 1;
+                Some(bytes.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Adds a block, evicting the least recently used ones to make room.
+    /// Blocks larger than the whole cache are not kept.
+    pub fn insert(&mut self, cid: Vec<u8>, bytes: Vec<u8>) {
+        let cost = cid.len() + bytes.len();
+        if cost > self.capacity || self.blocks.contains_key(&cid) {
+            return;
+        }
+        self.tick += 1;
+        self.size += cost;
+        self.recency.insert(self.tick, cid.clone());
+        self.blocks.insert(cid, (bytes, self.tick));
+        self.evict();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            capacity: self.capacity as u64,
+            size: self.size as u64,
+            entries: self.blocks.len() as u64,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.capacity {
+            let Some((_, cid)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some((bytes, _)) = self.blocks.remove(&cid) {
+                self.size -= cid.len() + bytes.len();
+            }
+        }
+    }
+}
+
+/// Serves reads from `cache` when it can and fills it from `inner` otherwise.
+/// Writes go straight through to `inner` and are cached once stored.
+#[derive(Clone)]
+pub struct CachedStore<S> {
+    inner: S,
+    cache: Arc<Mutex<BlockCache>>,
+}
+
+impl<S> CachedStore<S> {
+    pub fn new(inner: S, cache: Arc<Mutex<BlockCache>>) -> Self {
+        Self { inner, cache }
+    }
+
+    // A poisoned lock only means another call panicked mid-update; the cache
+    // contents are still valid blocks, so keep using them.
+    fn cache(&self) -> std::sync::MutexGuard<'_, BlockCache> {
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<'a, S: FFIStore<'a> + Clone + 'a> FFIStore<'a> for CachedStore<S> {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        if let Some(bytes) = self.cache().get(&cid) {
+            return Ok(bytes);
+        }
+        let bytes = self.inner.get_block(cid.clone())?;
+        self.cache().insert(cid, bytes.clone());
+        Ok(bytes)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        let enabled = self.cache().is_enabled();
+        if !enabled {
+            return self.inner.put_block(cid, bytes);
+        }
+        self.inner.put_block(cid.clone(), bytes.clone())?;
+        self.cache().insert(cid, bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Export of a forest, or of the part of it needed to read a subtree, as a
+//! CARv1 file: a DAG-CBOR header naming the root, followed by every block
+//! prefixed with its length and CID. The blocks are written as the store
+//! hands them over, so the export never holds more than one batch in memory.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::Result;
+use libipld::cbor::DagCborCodec;
+use libipld::codec::Codec;
+use libipld::{Cid, Ipld};
+use wnfsutils::blockstore::FFIStore;
+
+use crate::batch::{links, BlockBatch, BATCH_BLOCKS};
+use crate::core::WnfsDrive;
+
+/// Writes a CARv1 stream with a single root.
+pub struct CarWriter<W: Write> {
+    out: W,
+    written: HashSet<Vec<u8>>,
+}
+
+impl<W: Write> CarWriter<W> {
+    /// Writes the header naming `root`.
+    pub fn new(mut out: W, root: &Cid) -> Result<Self, String> {
+        let header = Ipld::Map(BTreeMap::from([
+            ("roots".to_string(), Ipld::List(vec![Ipld::Link(*root)])),
+            ("version".to_string(), Ipld::Integer(1)),
+        ]));
+        let header = DagCborCodec.encode(&header).map_err(|err| err.to_string())?;
+        write_varint(&mut out, header.len() as u64)?;
+        out.write_all(&header).map_err(|err| err.to_string())?;
+        Ok(Self { out, written: HashSet::new() })
+    }
+
+    /// Appends `block` unless a block with the same CID was written before.
+    pub fn write_block(&mut self, cid: &[u8], block: &[u8]) -> Result<(), String> {
+        if self.written.contains(cid) {
+            return Ok(());
+        }
+        write_varint(&mut self.out, (cid.len() + block.len()) as u64)?;
+        self.out.write_all(cid).map_err(|err| err.to_string())?;
+        self.out.write_all(block).map_err(|err| err.to_string())?;
+        self.written.insert(cid.to_vec());
+        Ok(())
+    }
+
+    /// Number of blocks written so far.
+    pub fn blocks(&self) -> u64 {
+        self.written.len() as u64
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+fn write_varint(out: &mut impl Write, mut value: u64) -> Result<(), String> {
+    let mut bytes = Vec::with_capacity(10);
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+    out.write_all(&bytes).map_err(|err| err.to_string())
+}
+
+/// Copies every block read through it into a `CarWriter`. Writes go straight
+/// to `inner`.
+struct ExportStore<S, W: Write> {
+    inner: S,
+    car: Rc<RefCell<CarWriter<W>>>,
+}
+
+impl<S: Clone, W: Write> Clone for ExportStore<S, W> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone(), car: self.car.clone() }
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a, W: Write + 'a> FFIStore<'a> for ExportStore<S, W> {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        let block = self.inner.get_block(cid.clone())?;
+        self.car.borrow_mut().write_block(&cid, &block).map_err(anyhow::Error::msg)?;
+        Ok(block)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        self.inner.put_block(cid, bytes)
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a, W: Write + 'a> BlockBatch<'a> for ExportStore<S, W> {
+    fn get_blocks(&self, cids: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        let blocks = self.inner.get_blocks(cids.clone())?;
+        let mut car = self.car.borrow_mut();
+        for (cid, block) in cids.iter().zip(&blocks) {
+            car.write_block(cid, block).map_err(anyhow::Error::msg)?;
+        }
+        Ok(blocks)
+    }
+}
+
+/// Writes the forest at `root` from `store` into a CARv1 file at `output`
+/// with `root` in its header, and returns the number of blocks written.
+/// With `subtree` only the blocks needed to open the forest and read
+/// everything under that path are written; otherwise every block reachable
+/// from `root` is. A failed export leaves no file behind.
+pub fn export_car<'a, S: BlockBatch<'a> + Clone + 'a>(
+    store: S,
+    root: Cid,
+    output: &Path,
+    subtree: Option<&[String]>,
+) -> Result<u64, String> {
+    let file = File::create(output).map_err(|err| format!("creating {}: {}", output.display(), err))?;
+    let result = write_car(store, root, BufWriter::new(file), subtree);
+    if result.is_err() {
+        let _ = std::fs::remove_file(output);
+    }
+    result
+}
+
+fn write_car<'a, S: BlockBatch<'a> + Clone + 'a, W: Write + 'a>(
+    store: S,
+    root: Cid,
+    out: W,
+    subtree: Option<&[String]>,
+) -> Result<u64, String> {
+    let car = Rc::new(RefCell::new(CarWriter::new(out, &root)?));
+    let store = ExportStore { inner: store, car: car.clone() };
+    // Opening the forest records the blocks it takes besides the ones linked from the root.
+    let drive = WnfsDrive::reload(store.clone(), root)?;
+    match subtree {
+        Some(path_segments) => drive.read_tree(path_segments)?,
+        None => fetch_reachable(&store, &root).map_err(|err| err.to_string())?,
+    }
+    drop(drive);
+    let mut car = car.borrow_mut();
+    car.flush()?;
+    Ok(car.blocks())
+}
+
+/// Fetches every block reachable from `root`, a batch of links at a time.
+fn fetch_reachable<'a, S: BlockBatch<'a>>(store: &S, root: &Cid) -> Result<()> {
+    let mut seen = HashSet::from([root.to_bytes()]);
+    let mut frontier = vec![root.to_bytes()];
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for chunk in frontier.chunks(BATCH_BLOCKS) {
+            let blocks = store.get_blocks(chunk.to_vec())?;
+            for (cid, block) in chunk.iter().zip(&blocks) {
+                for link in links(cid, block) {
+                    let link = link.to_bytes();
+                    if seen.insert(link.clone()) {
+                        next.push(link);
+                    }
+                }
+            }
+        }
+        frontier = next;
+    }
+    Ok(())
+}
@@ -0,0 +1,295 @@
+//! Platform-independent API over a private forest. `WnfsDrive` works with any
+//! `FFIStore`, so it can be used and tested without a JVM; the JNI exports in
+//! `android` only convert arguments and results around it.
+
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+
+use chrono::Utc;
+use libipld::Cid;
+use log::trace;
+use wnfsutils::blockstore::FFIFriendlyBlockStore;
+use wnfsutils::private_forest::PrivateDirectoryHelper;
+
+use crate::batch::Staging;
+use crate::{nodes, transaction, transfer};
+
+pub use crate::batch::{BatchedStore, BlockBatch, BATCH_BLOCKS, BATCH_BYTES};
+pub use crate::cache::{BlockCache, CacheStats, CachedStore};
+pub use crate::car::{export_car, CarWriter};
+pub use crate::dedup::{BlockQuery, DedupStore, PutCounters, PutPolicy, PutStats};
+pub use crate::disk_cache::{DiskCache, DiskCachedStore};
+pub use crate::nodes::{Entry, NodeInfo, NodeKind};
+pub use crate::progress::{Cancel, Progress, CANCELLED};
+pub use crate::transaction::Op;
+pub use crate::transfer::{Failure, TransferReport, UploadOptions};
+pub use crate::upload_queue::{PendingUploads, QueuedStore, UploadQueue};
+pub use wnfsutils::blockstore::FFIStore;
+
+/// Error codes reported by every binding. Exported to C as `WnfsErrorCode`;
+/// keep in sync with `land.fx.wnfslib.exceptions.ErrorCode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ErrorCode {
+    Unknown = 0,
+    NotFound = 1,
+    AlreadyExists = 2,
+    NotADirectory = 3,
+    NotAFile = 4,
+    InvalidPath = 5,
+    InvalidCid = 6,
+    WrongKey = 7,
+    StoreUnavailable = 8,
+    StoreMismatch = 9,
+    Corrupted = 10,
+    Cancelled = 11,
+}
+
+impl ErrorCode {
+    /// Classifies an error message coming back from wnfs/wnfsutils, which report
+    /// failures as strings.
+    pub fn from_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+        if matches(&[CANCELLED]) {
+            ErrorCode::Cancelled
+        } else if matches(&["block not available", "store unavailable"]) {
+            ErrorCode::StoreUnavailable
+        } else if matches(&["store mismatch"]) {
+            ErrorCode::StoreMismatch
+        } else if matches(&["invalid cid", "failed to parse cid"]) {
+            ErrorCode::InvalidCid
+        } else if matches(&["already exists"]) {
+            ErrorCode::AlreadyExists
+        } else if matches(&["expected a directory", "not a directory"]) {
+            ErrorCode::NotADirectory
+        } else if matches(&["expected a file", "not a file"]) {
+            ErrorCode::NotAFile
+        } else if matches(&["invalid path", "invalid filename"]) {
+            ErrorCode::InvalidPath
+        } else if matches(&["cannot find", "not found", "no such"]) {
+            ErrorCode::NotFound
+        } else if matches(&["decrypt", "wrong key", "aead"]) {
+            ErrorCode::WrongKey
+        } else if matches(&["deserializ", "decode", "cbor", "corrupt"]) {
+            ErrorCode::Corrupted
+        } else {
+            ErrorCode::Unknown
+        }
+    }
+}
+
+/// Runs the body of an exported call, turning a panic into an error message so
+/// that it never unwinds across the FFI boundary and aborts the app.
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let reason = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_owned());
+        trace!("wnfsError panic caught at the FFI boundary: {}", reason);
+        Err(format!("panic in native code: {}", reason))
+    })
+}
+
+/// A private forest loaded from a block store. Every mutating call stores
+/// the forest and returns its new root CID, which is what `load` and
+/// `reload` take to pick up from there later.
+pub struct WnfsDrive<'a> {
+    helper: PrivateDirectoryHelper<'a>,
+    /// The `BatchedStore` holding back blocks written through this drive.
+    batch: Option<Box<dyn Staging + 'a>>,
+}
+
+impl<'a> WnfsDrive<'a> {
+    /// Creates an empty forest keyed by `wnfs_key` and returns it with its root CID.
+    pub fn init(store: impl FFIStore<'a> + 'a, wnfs_key: Vec<u8>) -> Result<(Self, Cid), String> {
+        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
+        let (helper, _, cid) = PrivateDirectoryHelper::synced_init(block_store, wnfs_key)?;
+        Ok((Self { helper, batch: None }, cid))
+    }
+
+    /// Opens the forest at `forest_cid` with the key it was created with.
+    pub fn load(store: impl FFIStore<'a> + 'a, forest_cid: Cid, wnfs_key: Vec<u8>) -> Result<Self, String> {
+        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
+        let helper = PrivateDirectoryHelper::synced_load_with_wnfs_key(block_store, forest_cid, wnfs_key)?;
+        Ok(Self { helper, batch: None })
+    }
+
+    /// Opens the forest at `forest_cid` using the root stored alongside it,
+    /// for forests that were already loaded once with their key.
+    pub fn reload(store: impl FFIStore<'a> + 'a, forest_cid: Cid) -> Result<Self, String> {
+        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
+        let helper = PrivateDirectoryHelper::synced_reload(block_store, forest_cid)?;
+        Ok(Self { helper, batch: None })
+    }
+
+    /// Flushes `store` after every operation that commits, so the returned root
+    /// CID is only handed out once its blocks are stored. A write-back `store`
+    /// is only flushed by `flush`. `store` should be the one, or a clone of
+    /// the one, the drive was opened with.
+    pub fn with_batch<S: BlockBatch<'a> + Clone + 'a>(mut self, store: BatchedStore<S>) -> Self {
+        self.batch = Some(Box::new(store));
+        self
+    }
+
+    /// Sends every block still held back by the store the drive was opened with.
+    pub fn send_pending(&self) -> Result<(), String> {
+        match &self.batch {
+            Some(batch) => batch.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Commits the current state and sends the held-back blocks it refers to.
+    /// With a write-back store, blocks only earlier states referred to are
+    /// dropped instead. Returns the root CID, which is readable from the
+    /// `Datastore` alone once this returns.
+    pub fn flush(&mut self) -> Result<Cid, String> {
+        let root = nodes::commit(&mut self.helper)?;
+        if let Some(batch) = &self.batch {
+            batch.flush_reachable(&root)?;
+        }
+        Ok(root)
+    }
+
+    fn committed<T>(&self, result: Result<T, String>) -> Result<T, String> {
+        let value = result?;
+        match &self.batch {
+            Some(batch) if !batch.is_write_back() => batch.flush()?,
+            _ => {}
+        }
+        Ok(value)
+    }
+
+    /// Splits a `/`-separated path into the segments the other calls take.
+    /// An empty path is the root directory.
+    pub fn parse_path(path: &str) -> Vec<String> {
+        PrivateDirectoryHelper::parse_path(path.to_string())
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    pub fn ls(&self, path_segments: &[String]) -> Result<Vec<Entry>, String> {
+        nodes::ls(&self.helper, path_segments)
+    }
+
+    pub fn stat(&self, path_segments: &[String]) -> Result<NodeInfo, String> {
+        nodes::stat(&self.helper, path_segments)
+    }
+
+    pub fn exists(&self, path_segments: &[String]) -> Result<Option<NodeKind>, String> {
+        nodes::exists(&self.helper, path_segments)
+    }
+
+    pub fn read(&mut self, path_segments: &[String]) -> Result<Vec<u8>, String> {
+        self.helper.synced_read_file(path_segments)
+    }
+
+    /// Reads `length` bytes from `offset`, or everything after it when `length` is `None`.
+    pub fn read_range(&self, path_segments: &[String], offset: u64, length: Option<usize>) -> Result<Vec<u8>, String> {
+        nodes::read_range(&self.helper, path_segments, offset, length)
+    }
+
+    pub fn read_to_path(&mut self, path_segments: &[String], filename: &str) -> Result<(), String> {
+        self.helper.synced_read_file_to_path(path_segments, &filename.to_string()).map(|_| ())
+    }
+
+    /// Streams the file into `filename` starting at `offset`, keeping the
+    /// local bytes before it, so an interrupted download can be resumed.
+    pub fn read_stream_to_path(
+        &mut self,
+        path_segments: &[String],
+        filename: &str,
+        offset: u64,
+        progress: &mut Progress,
+        cancel: &Cancel,
+    ) -> Result<(), String> {
+        if offset == 0 && progress.is_none() && cancel.is_none() {
+            return self.helper.synced_read_filestream_to_path(&filename.to_string(), path_segments, 0).map(|_| ());
+        }
+        nodes::read_to_path(&self.helper, path_segments, filename, offset, progress, cancel)
+    }
+
+    /// Reads everything under `path_segments` without keeping it, which
+    /// fetches every block needed to read that part of the tree.
+    pub fn read_tree(&self, path_segments: &[String]) -> Result<(), String> {
+        nodes::read_tree(&self.helper, path_segments)
+    }
+
+    pub fn read_dir_to_path(
+        &self,
+        path_segments: &[String],
+        local_dir: &Path,
+        classify: &dyn Fn(&str) -> i32,
+    ) -> Result<TransferReport, String> {
+        transfer::read_dir_to_path(&self.helper, path_segments, local_dir, classify)
+    }
+
+    pub fn write(&mut self, path_segments: &[String], content: Vec<u8>) -> Result<Cid, String> {
+        let result = self.helper.synced_write_file(path_segments, content, 0);
+        self.committed(result)
+    }
+
+    pub fn write_from_path(&mut self, path_segments: &[String], filename: &str) -> Result<Cid, String> {
+        let result = self.helper.synced_write_file_from_path(path_segments, &filename.to_string());
+        self.committed(result)
+    }
+
+    /// Streams `filename` into the forest. Nothing is committed until the
+    /// whole file is in, so a cancelled upload leaves the previous root current.
+    pub fn write_stream_from_path(
+        &mut self,
+        path_segments: &[String],
+        filename: &str,
+        progress: &mut Progress,
+        cancel: &Cancel,
+    ) -> Result<Cid, String> {
+        if progress.is_none() && cancel.is_none() {
+            let result = self.helper.synced_write_file_stream_from_path(path_segments, &filename.to_string());
+            return self.committed(result);
+        }
+        nodes::stream_from_path(&mut self.helper, path_segments, Path::new(filename), Utc::now(), progress, cancel)?;
+        let result = nodes::commit(&mut self.helper);
+        self.committed(result)
+    }
+
+    pub fn write_dir_from_path(
+        &mut self,
+        path_segments: &[String],
+        local_dir: &Path,
+        options: &UploadOptions,
+        classify: &dyn Fn(&str) -> i32,
+    ) -> Result<TransferReport, String> {
+        let result = transfer::write_dir_from_path(&mut self.helper, path_segments, local_dir, options, classify);
+        self.committed(result)
+    }
+
+    pub fn mkdir(&mut self, path_segments: &[String]) -> Result<Cid, String> {
+        let result = self.helper.synced_mkdir(path_segments);
+        self.committed(result)
+    }
+
+    pub fn rm(&mut self, path_segments: &[String]) -> Result<Cid, String> {
+        let result = self.helper.synced_rm(path_segments);
+        self.committed(result)
+    }
+
+    pub fn mv(&mut self, source_path_segments: &[String], target_path_segments: &[String]) -> Result<Cid, String> {
+        let result = self.helper.synced_mv(source_path_segments, target_path_segments);
+        self.committed(result)
+    }
+
+    pub fn cp(&mut self, source_path_segments: &[String], target_path_segments: &[String]) -> Result<Cid, String> {
+        let result = self.helper.synced_cp(source_path_segments, target_path_segments);
+        self.committed(result)
+    }
+
+    /// Applies `ops` in order and commits once; on failure nothing is committed.
+    pub fn apply(&mut self, ops: Vec<Op>) -> Result<Cid, String> {
+        let result = transaction::apply(&mut self.helper, ops);
+        self.committed(result)
+    }
+}
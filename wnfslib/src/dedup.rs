@@ -0,0 +1,203 @@
+//! Skipping uploads of blocks the store already holds. Re-uploading a file
+//! that shares content with an earlier version produces many blocks that are
+//! already stored; `DedupStore` asks the store (`BlockQuery`) or trusts the
+//! persistent cache before passing a put on. It sits below `BatchedStore`, so
+//! it only sees the blocks a flush actually sends, and asks about a whole
+//! batch at once.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+use wnfsutils::blockstore::FFIStore;
+
+use crate::batch::BlockBatch;
+use crate::disk_cache::DiskCache;
+
+/// When a block is sent to the store. Keep in sync with `land.fx.wnfslib.PutPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum PutPolicy {
+    /// Every block is put.
+    Always = 0,
+    /// Blocks the store says it has are skipped.
+    CheckFirst = 1,
+    /// Blocks in the persistent cache are assumed stored and skipped; the
+    /// rest are checked as with `CheckFirst`.
+    TrustCache = 2,
+}
+
+impl PutPolicy {
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(PutPolicy::Always),
+            1 => Some(PutPolicy::CheckFirst),
+            2 => Some(PutPolicy::TrustCache),
+            _ => None,
+        }
+    }
+}
+
+/// A block store that may be able to tell whether it holds a block.
+pub trait BlockQuery<'a>: FFIStore<'a> {
+    /// `None` when the store cannot tell, in which case the block is put.
+    fn has_block(&self, _cid: &[u8]) -> Result<Option<bool>> {
+        Ok(None)
+    }
+
+    /// Answers for each of `cids`, in the same order. The default asks one at a time.
+    fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+        cids.iter().map(|cid| self.has_block(cid)).collect()
+    }
+}
+
+/// Answers `cids` from `known` where it can, and asks `inner` about the rest
+/// in one `has_blocks` call.
+pub(crate) fn has_blocks_after<'a, S: BlockQuery<'a>>(
+    inner: &S,
+    cids: &[Vec<u8>],
+    known: impl Fn(&[u8]) -> bool,
+) -> Result<Vec<Option<bool>>> {
+    let mut answers: Vec<_> = cids.iter().map(|cid| known(cid.as_slice()).then_some(true)).collect();
+    let unknown: Vec<usize> = (0..cids.len()).filter(|&i| answers[i].is_none()).collect();
+    if unknown.is_empty() {
+        return Ok(answers);
+    }
+    let query: Vec<_> = unknown.iter().map(|&i| cids[i].clone()).collect();
+    for (i, answer) in unknown.into_iter().zip(inner.has_blocks(&query)?) {
+        answers[i] = answer;
+    }
+    Ok(answers)
+}
+
+#[derive(Default)]
+pub struct PutStats {
+    puts: AtomicU64,
+    put_bytes: AtomicU64,
+    checks: AtomicU64,
+    skipped: AtomicU64,
+    skipped_bytes: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PutCounters {
+    pub puts: u64,
+    pub put_bytes: u64,
+    pub checks: u64,
+    pub skipped: u64,
+    pub skipped_bytes: u64,
+}
+
+impl PutStats {
+    pub fn counters(&self) -> PutCounters {
+        PutCounters {
+            puts: self.puts.load(Ordering::Relaxed),
+            put_bytes: self.put_bytes.load(Ordering::Relaxed),
+            checks: self.checks.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            skipped_bytes: self.skipped_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn put(&self, bytes: usize) {
+        self.puts.fetch_add(1, Ordering::Relaxed);
+        self.put_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn check(&self) {
+        self.checks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn skip(&self, bytes: usize) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        self.skipped_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// Applies a `PutPolicy` to the puts passed to `inner` and counts them in `stats`.
+#[derive(Clone)]
+pub struct DedupStore<S> {
+    inner: S,
+    policy: PutPolicy,
+    /// Consulted under `PutPolicy::TrustCache`.
+    cache: Option<Arc<DiskCache>>,
+    stats: Arc<PutStats>,
+}
+
+impl<S> DedupStore<S> {
+    pub fn new(inner: S, policy: PutPolicy, cache: Option<Arc<DiskCache>>, stats: Arc<PutStats>) -> Self {
+        Self { inner, policy, cache, stats }
+    }
+}
+
+impl<'a, S: BlockQuery<'a>> DedupStore<S> {
+    /// Whether each of `cids` can be skipped. Under `TrustCache` the
+    /// persistent cache answers first; the store is asked about the rest in
+    /// one query.
+    fn stored(&self, cids: &[Vec<u8>]) -> Result<Vec<bool>> {
+        if self.policy == PutPolicy::Always {
+            return Ok(vec![false; cids.len()]);
+        }
+        let cached = |cid: &[u8]| {
+            self.policy == PutPolicy::TrustCache && self.cache.as_ref().is_some_and(|cache| cache.contains(cid))
+        };
+        let answers = has_blocks_after(&self.inner, cids, &cached)?;
+        Ok(cids
+            .iter()
+            .zip(answers)
+            .map(|(cid, answer)| {
+                if answer.is_some() && !cached(cid.as_slice()) {
+                    self.stats.check();
+                }
+                answer == Some(true)
+            })
+            .collect())
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + BlockQuery<'a> + Clone + 'a> FFIStore<'a> for DedupStore<S> {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        self.inner.get_block(cid)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        self.put_blocks(vec![(cid, bytes)])
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + BlockQuery<'a> + Clone + 'a> BlockBatch<'a> for DedupStore<S> {
+    fn get_blocks(&self, cids: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        self.inner.get_blocks(cids)
+    }
+
+    fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        let cids: Vec<_> = blocks.iter().map(|(cid, _)| cid.clone()).collect();
+        let mut put = Vec::new();
+        for (block, stored) in blocks.into_iter().zip(self.stored(&cids)?) {
+            if stored {
+                self.stats.skip(block.1.len());
+            } else {
+                put.push(block);
+            }
+        }
+        if put.is_empty() {
+            return Ok(());
+        }
+        let sizes: Vec<_> = put.iter().map(|(_, bytes)| bytes.len()).collect();
+        self.inner.put_blocks(put)?;
+        sizes.into_iter().for_each(|len| self.stats.put(len));
+        Ok(())
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + BlockQuery<'a> + Clone + 'a> BlockQuery<'a> for DedupStore<S> {
+    fn has_block(&self, cid: &[u8]) -> Result<Option<bool>> {
+        self.inner.has_block(cid)
+    }
+
+    fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+        self.inner.has_blocks(cids)
+    }
+}
@@ -0,0 +1,324 @@
+//! Persistent block cache in a `kv` store on local disk, so that a cold start
+//! can reopen recently used folders without going back to the `Datastore`.
+//! Blocks are encrypted with AES-256-GCM under a key derived from the one the
+//! app supplies, and entries are named by a keyed hash of their CID. Once the
+//! cache holds more than its capacity the least recently used entries go first.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Result;
+use kv::{Bucket, Config, Raw, Store};
+use log::trace;
+use openssl::sha::Sha256;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use wnfsutils::blockstore::FFIStore;
+
+use crate::batch::BlockBatch;
+use crate::cache::CacheStats;
+use crate::dedup::BlockQuery;
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+pub struct DiskCache {
+    // Kept open for as long as the buckets are in use.
+    _store: Store,
+    /// Entry name -> nonce, tag and encrypted block.
+    blocks: Bucket<'static, Raw, Raw>,
+    /// Entry name -> tick of last use and stored size, both big-endian u64.
+    meta: Bucket<'static, Raw, Raw>,
+    key: [u8; 32],
+    capacity: u64,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    size: u64,
+    tick: u64,
+    /// Entry name -> (tick of last use, stored size).
+    entries: HashMap<Vec<u8>, (u64, u64)>,
+    /// Tick of last use -> entry name, oldest first.
+    recency: BTreeMap<u64, Vec<u8>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl DiskCache {
+    /// Opens or creates the cache in `dir`. Entries written under a different
+    /// `key` cannot be read back and are dropped when next looked up.
+    pub fn open(dir: &Path, capacity: u64, key: &[u8]) -> Result<Self, String> {
+        let store = Store::new(Config::new(dir)).map_err(|err| format!("opening block cache {}: {}", dir.display(), err))?;
+        let blocks = store.bucket::<Raw, Raw>(Some("blocks")).map_err(|err| err.to_string())?;
+        let meta = store.bucket::<Raw, Raw>(Some("meta")).map_err(|err| err.to_string())?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"wnfslib block cache");
+        hasher.update(key);
+        let cache = Self { _store: store, blocks, meta, key: hasher.finish(), capacity, state: Mutex::default() };
+
+        {
+            let mut state = cache.state();
+            for item in cache.meta.iter() {
+                let item = item.map_err(|err| err.to_string())?;
+                let name: Raw = item.key().map_err(|err| err.to_string())?;
+                let value: Raw = item.value().map_err(|err| err.to_string())?;
+                let Some((tick, size)) = decode_meta(&value) else {
+                    continue;
+                };
+                state.tick = state.tick.max(tick);
+                state.size += size;
+                state.recency.insert(tick, name.to_vec());
+                state.entries.insert(name.to_vec(), (tick, size));
+            }
+        }
+        cache.evict()?;
+        Ok(cache)
+    }
+
+    pub fn get(&self, cid: &[u8]) -> Option<Vec<u8>> {
+        let name = self.entry_name(cid);
+        let stored = match self.blocks.get(&Raw::from(name.as_slice())) {
+            Ok(stored) => stored,
+            Err(err) => {
+                trace!("wnfsError block cache read failed: {}", err);
+                None
+            }
+        };
+        let block = stored.and_then(|stored| self.decrypt(&name, &stored).map(|block| (block, stored.len())));
+        let mut state = self.state();
+        match block {
+            Some((block, stored_len)) => {
+                state.hits += 1;
+                if let Err(err) = self.touch(&mut state, &name, (name.len() + stored_len) as u64) {
+                    trace!("wnfsError block cache update failed: {}", err);
+                }
+                Some(block)
+            }
+            None => {
+                state.misses += 1;
+                // Unreadable entries (another key, a torn write) are not worth keeping.
+                if state.entries.contains_key(&name) {
+                    let _ = self.remove(&mut state, &name);
+                }
+                None
+            }
+        }
+    }
+
+    /// Whether `cid` is cached, without counting a hit or miss.
+    pub fn contains(&self, cid: &[u8]) -> bool {
+        let name = self.entry_name(cid);
+        self.state().entries.contains_key(&name)
+    }
+
+    pub fn insert(&self, cid: &[u8], block: &[u8]) {
+        if let Err(err) = self.try_insert(cid, block) {
+            trace!("wnfsError block cache write failed: {}", err);
+        }
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        let mut state = self.state();
+        self.blocks.clear().map_err(|err| err.to_string())?;
+        self.meta.clear().map_err(|err| err.to_string())?;
+        self.blocks.flush().map_err(|err| err.to_string())?;
+        *state = State::default();
+        Ok(())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state();
+        CacheStats {
+            capacity: self.capacity,
+            size: state.size,
+            entries: state.entries.len() as u64,
+            hits: state.hits,
+            misses: state.misses,
+        }
+    }
+
+    fn try_insert(&self, cid: &[u8], block: &[u8]) -> Result<(), String> {
+        let name = self.entry_name(cid);
+        let stored = self.encrypt(&name, block)?;
+        let size = (name.len() + stored.len()) as u64;
+        if size > self.capacity {
+            return Ok(());
+        }
+        let mut state = self.state();
+        if let Some(&(_, size)) = state.entries.get(&name) {
+            return self.touch(&mut state, &name, size);
+        }
+        self.blocks
+            .set(&Raw::from(name.as_slice()), &Raw::from(stored))
+            .map_err(|err| err.to_string())?;
+        self.touch(&mut state, &name, size)?;
+        drop(state);
+        self.evict()
+    }
+
+    /// Records `name` as just used and `size` bytes big, in memory and on disk.
+    fn touch(&self, state: &mut State, name: &[u8], size: u64) -> Result<(), String> {
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((previous, previous_size)) = state.entries.insert(name.to_vec(), (tick, size)) {
+            state.recency.remove(&previous);
+            state.size -= previous_size;
+        }
+        state.recency.insert(tick, name.to_vec());
+        state.size += size;
+        self.meta
+            .set(&Raw::from(name), &Raw::from(encode_meta(tick, size).as_slice()))
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    fn evict(&self) -> Result<(), String> {
+        let mut state = self.state();
+        while state.size > self.capacity {
+            let Some((_, name)) = state.recency.pop_first() else {
+                break;
+            };
+            self.remove(&mut state, &name)?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, state: &mut State, name: &[u8]) -> Result<(), String> {
+        if let Some((tick, size)) = state.entries.remove(name) {
+            state.recency.remove(&tick);
+            state.size -= size;
+        }
+        self.blocks.remove(&Raw::from(name)).map_err(|err| err.to_string())?;
+        self.meta.remove(&Raw::from(name)).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    fn entry_name(&self, cid: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.key);
+        hasher.update(cid);
+        hasher.finish().to_vec()
+    }
+
+    fn encrypt(&self, name: &[u8], block: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        openssl::rand::rand_bytes(&mut nonce).map_err(|err| err.to_string())?;
+        let mut tag = [0u8; TAG_LEN];
+        let ciphertext = encrypt_aead(Cipher::aes_256_gcm(), &self.key, Some(&nonce), name, block, &mut tag)
+            .map_err(|err| err.to_string())?;
+        Ok([nonce.as_slice(), tag.as_slice(), ciphertext.as_slice()].concat())
+    }
+
+    fn decrypt(&self, name: &[u8], stored: &[u8]) -> Option<Vec<u8>> {
+        if stored.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+        let (nonce, rest) = stored.split_at(NONCE_LEN);
+        let (tag, ciphertext) = rest.split_at(TAG_LEN);
+        decrypt_aead(Cipher::aes_256_gcm(), &self.key, Some(nonce), name, ciphertext, tag).ok()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn encode_meta(tick: u64, size: u64) -> [u8; 16] {
+    let mut meta = [0u8; 16];
+    meta[..8].copy_from_slice(&tick.to_be_bytes());
+    meta[8..].copy_from_slice(&size.to_be_bytes());
+    meta
+}
+
+fn decode_meta(meta: &[u8]) -> Option<(u64, u64)> {
+    let tick = u64::from_be_bytes(meta.get(..8)?.try_into().ok()?);
+    let size = u64::from_be_bytes(meta.get(8..16)?.try_into().ok()?);
+    Some((tick, size))
+}
+
+/// Serves reads from the disk cache when one is set and fills it from `inner`
+/// otherwise. Writes are stored in `inner` first and then cached.
+#[derive(Clone)]
+pub struct DiskCachedStore<S> {
+    inner: S,
+    cache: Option<Arc<DiskCache>>,
+}
+
+impl<S> DiskCachedStore<S> {
+    pub fn new(inner: S, cache: Option<Arc<DiskCache>>) -> Self {
+        Self { inner, cache }
+    }
+}
+
+impl<'a, S: FFIStore<'a> + Clone + 'a> FFIStore<'a> for DiskCachedStore<S> {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        let Some(cache) = &self.cache else {
+            return self.inner.get_block(cid);
+        };
+        if let Some(block) = cache.get(&cid) {
+            return Ok(block);
+        }
+        let block = self.inner.get_block(cid.clone())?;
+        cache.insert(&cid, &block);
+        Ok(block)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        let Some(cache) = &self.cache else {
+            return self.inner.put_block(cid, bytes);
+        };
+        self.inner.put_block(cid.clone(), bytes.clone())?;
+        cache.insert(&cid, &bytes);
+        Ok(())
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a> BlockBatch<'a> for DiskCachedStore<S> {
+    fn get_blocks(&self, cids: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        let Some(cache) = &self.cache else {
+            return self.inner.get_blocks(cids);
+        };
+        let cached: Vec<_> = cids.iter().map(|cid| cache.get(cid)).collect();
+        let missing: Vec<_> = cids.iter().zip(&cached).filter(|(_, block)| block.is_none()).map(|(cid, _)| cid.clone()).collect();
+        if missing.is_empty() {
+            return Ok(cached.into_iter().flatten().collect());
+        }
+        let mut fetched = self.inner.get_blocks(missing)?.into_iter();
+        let mut blocks = Vec::with_capacity(cids.len());
+        for (cid, block) in cids.iter().zip(cached) {
+            match block {
+                Some(block) => blocks.push(block),
+                None => {
+                    let block = fetched.next().ok_or_else(|| anyhow::anyhow!("store returned fewer blocks than asked for"))?;
+                    cache.insert(cid, &block);
+                    blocks.push(block);
+                }
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        let Some(cache) = &self.cache else {
+            return self.inner.put_blocks(blocks);
+        };
+        self.inner.put_blocks(blocks.clone())?;
+        for (cid, bytes) in &blocks {
+            cache.insert(cid, bytes);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: BlockQuery<'a> + Clone + 'a> BlockQuery<'a> for DiskCachedStore<S> {
+    fn has_block(&self, cid: &[u8]) -> Result<Option<bool>> {
+        self.inner.has_block(cid)
+    }
+
+    fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+        self.inner.has_blocks(cids)
+    }
+}
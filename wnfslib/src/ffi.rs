@@ -0,0 +1,339 @@
+//! C ABI over `WnfsDrive` for iOS, Flutter (`dart:ffi`) and React Native.
+//! `build.rs` generates the header, `wnfs.h`, into `OUT_DIR/include`; the copy
+//! in `include/` is refreshed with `WNFS_HEADER_DIR=include`.
+//!
+//! Every call that can fail returns `true` on success. On failure it returns
+//! `false` and the reason is available from `wnfs_last_error_code` and
+//! `wnfs_last_error_message` on the same thread until the next call.
+//! A drive must not be used from two threads at once.
+
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use libipld::Cid;
+use log::trace;
+
+use crate::core::{catch_panic, ErrorCode, FFIStore, NodeKind, WnfsDrive};
+
+/// Bytes passed across the boundary. Buffers returned by the library must be
+/// released with `wnfs_buffer_free`.
+#[repr(C)]
+pub struct WnfsBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Caller-supplied block store, the C counterpart of the `Datastore` used on
+/// Android. Callbacks return `true` on success and receive `context` as their
+/// first argument.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct WnfsBlockStore {
+    pub context: *mut c_void,
+    /// Fills `out` with the block stored under `cid`. The memory stays owned
+    /// by the caller and is handed back through `release_block` once copied.
+    /// Returning `true` with `out->data` left null counts as a missing block.
+    pub get_block: extern "C" fn(context: *mut c_void, cid: *const u8, cid_len: usize, out: *mut WnfsBuffer) -> bool,
+    /// Stores `data` under `cid`.
+    pub put_block:
+        extern "C" fn(context: *mut c_void, cid: *const u8, cid_len: usize, data: *const u8, data_len: usize) -> bool,
+    /// Releases a buffer filled by `get_block`. May be null if nothing needs releasing.
+    pub release_block: Option<extern "C" fn(context: *mut c_void, buffer: WnfsBuffer)>,
+}
+
+/// Result of `wnfs_exists`.
+#[repr(i32)]
+pub enum WnfsNodeKind {
+    None = 0,
+    File = 1,
+    Directory = 2,
+}
+
+impl<'a> FFIStore<'a> for WnfsBlockStore {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        let mut out = WnfsBuffer { data: ptr::null_mut(), len: 0 };
+        if !(self.get_block)(self.context, cid.as_ptr(), cid.len(), &mut out) {
+            STORE_FAILED.with(|failed| failed.set(true));
+            return Err(anyhow!("block not available: get_block failed for {}", describe_cid(&cid)));
+        }
+        if out.data.is_null() {
+            STORE_FAILED.with(|failed| failed.set(true));
+            return Err(anyhow!("block not available: get_block returned no data for {}", describe_cid(&cid)));
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(out.data, out.len) }.to_vec();
+        if let Some(release_block) = self.release_block {
+            release_block(self.context, out);
+        }
+        Ok(bytes)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        if !(self.put_block)(self.context, cid.as_ptr(), cid.len(), bytes.as_ptr(), bytes.len()) {
+            STORE_FAILED.with(|failed| failed.set(true));
+            return Err(anyhow!("store unavailable: put_block failed for {}", describe_cid(&cid)));
+        }
+        Ok(())
+    }
+}
+
+fn describe_cid(cid: &[u8]) -> String {
+    Cid::try_from(cid).map(|cid| cid.to_string()).unwrap_or_else(|_| format!("{:?}", cid))
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(ErrorCode, CString)>> = const { RefCell::new(None) };
+    /// Set when a store callback fails. wnfs only hands the failure back as
+    /// text, and not always with the callback's message in it.
+    static STORE_FAILED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f`, recording its error for `wnfs_last_error_*`.
+fn guard(name: &str, f: impl FnOnce() -> Result<(), String>) -> bool {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    STORE_FAILED.with(|failed| failed.set(false));
+    match catch_panic(f) {
+        Ok(()) => true,
+        Err(msg) => {
+            trace!("wnfsError in {}: {:?}", name, msg);
+            let code = if STORE_FAILED.with(|failed| failed.take()) {
+                ErrorCode::StoreUnavailable
+            } else {
+                ErrorCode::from_message(&msg)
+            };
+            let message = CString::new(msg.replace('\0', " ")).unwrap_or_default();
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some((code, message)));
+            false
+        }
+    }
+}
+
+fn deserialize_str<'s>(text: *const c_char, what: &str) -> Result<&'s str, String> {
+    if text.is_null() {
+        return Err(format!("invalid {}: null", what));
+    }
+    unsafe { CStr::from_ptr(text) }
+        .to_str()
+        .map_err(|err| format!("invalid {}: {}", what, err))
+}
+
+fn deserialize_path(path: *const c_char) -> Result<Vec<String>, String> {
+    deserialize_str(path, "path").map(WnfsDrive::parse_path)
+}
+
+fn deserialize_cid(cid: *const c_char) -> Result<Cid, String> {
+    Cid::try_from(deserialize_str(cid, "cid")?).map_err(|err| format!("invalid cid: {}", err))
+}
+
+fn deserialize_bytes<'b>(data: *const u8, len: usize) -> Result<&'b [u8], String> {
+    match (data.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err("invalid buffer: null".to_string()),
+        (false, _) => Ok(unsafe { std::slice::from_raw_parts(data, len) }),
+    }
+}
+
+fn drive_mut<'d>(drive: *mut WnfsDrive<'static>) -> Result<&'d mut WnfsDrive<'static>, String> {
+    unsafe { drive.as_mut() }.ok_or_else(|| "invalid drive: null".to_string())
+}
+
+fn write_out<T>(out: *mut T, value: T) -> Result<(), String> {
+    if out.is_null() {
+        return Err("invalid output: null".to_string());
+    }
+    unsafe { out.write(value) };
+    Ok(())
+}
+
+fn serialize_buffer(bytes: Vec<u8>, out: *mut WnfsBuffer) -> Result<(), String> {
+    let len = bytes.len();
+    let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+    write_out(out, WnfsBuffer { data, len })
+}
+
+/// The root CID is optional output, so a null `out_cid` is not an error.
+fn serialize_cid(cid: Cid, out_cid: *mut *mut c_char) -> Result<(), String> {
+    if out_cid.is_null() {
+        return Ok(());
+    }
+    let text = CString::new(cid.to_string()).map_err(|err| err.to_string())?;
+    write_out(out_cid, text.into_raw())
+}
+
+fn serialize_drive(drive: WnfsDrive<'static>, out_drive: *mut *mut WnfsDrive<'static>) -> Result<(), String> {
+    write_out(out_drive, Box::into_raw(Box::new(drive)))
+}
+
+/// Creates an empty forest. `out_drive` receives a handle to free with
+/// `wnfs_drive_free`; `out_cid`, if not null, the root CID to free with `wnfs_string_free`.
+#[no_mangle]
+pub extern "C" fn wnfs_init(
+    store: WnfsBlockStore,
+    wnfs_key: *const u8,
+    wnfs_key_len: usize,
+    out_drive: *mut *mut WnfsDrive<'static>,
+    out_cid: *mut *mut c_char,
+) -> bool {
+    guard("wnfs_init", || {
+        let wnfs_key = deserialize_bytes(wnfs_key, wnfs_key_len)?.to_vec();
+        let (drive, cid) = WnfsDrive::init(store, wnfs_key)?;
+        serialize_drive(drive, out_drive)?;
+        serialize_cid(cid, out_cid)
+    })
+}
+
+/// Opens the forest at `cid` with the key it was created with.
+#[no_mangle]
+pub extern "C" fn wnfs_load(
+    store: WnfsBlockStore,
+    cid: *const c_char,
+    wnfs_key: *const u8,
+    wnfs_key_len: usize,
+    out_drive: *mut *mut WnfsDrive<'static>,
+) -> bool {
+    guard("wnfs_load", || {
+        let cid = deserialize_cid(cid)?;
+        let wnfs_key = deserialize_bytes(wnfs_key, wnfs_key_len)?.to_vec();
+        let drive = WnfsDrive::load(store, cid, wnfs_key)?;
+        serialize_drive(drive, out_drive)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_drive_free(drive: *mut WnfsDrive<'static>) {
+    free_box(drive);
+}
+
+/// Lists a directory as the same JSON array `Fs.ls` returns on Android.
+#[no_mangle]
+pub extern "C" fn wnfs_ls(drive: *mut WnfsDrive<'static>, path: *const c_char, out_json: *mut WnfsBuffer) -> bool {
+    guard("wnfs_ls", || {
+        let entries = drive_mut(drive)?.ls(&deserialize_path(path)?)?;
+        serialize_buffer(serde_json::to_vec(&entries).map_err(|err| err.to_string())?, out_json)
+    })
+}
+
+/// Describes a file or directory as the same JSON object `Fs.stat` returns on Android.
+#[no_mangle]
+pub extern "C" fn wnfs_stat(drive: *mut WnfsDrive<'static>, path: *const c_char, out_json: *mut WnfsBuffer) -> bool {
+    guard("wnfs_stat", || {
+        let info = drive_mut(drive)?.stat(&deserialize_path(path)?)?;
+        serialize_buffer(serde_json::to_vec(&info).map_err(|err| err.to_string())?, out_json)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_exists(drive: *mut WnfsDrive<'static>, path: *const c_char, out_kind: *mut WnfsNodeKind) -> bool {
+    guard("wnfs_exists", || {
+        let kind = match drive_mut(drive)?.exists(&deserialize_path(path)?)? {
+            Some(NodeKind::File) => WnfsNodeKind::File,
+            Some(NodeKind::Directory) => WnfsNodeKind::Directory,
+            None => WnfsNodeKind::None,
+        };
+        write_out(out_kind, kind)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_read(drive: *mut WnfsDrive<'static>, path: *const c_char, out_content: *mut WnfsBuffer) -> bool {
+    guard("wnfs_read", || {
+        let content = drive_mut(drive)?.read(&deserialize_path(path)?)?;
+        serialize_buffer(content, out_content)
+    })
+}
+
+/// Writes a file, creating missing parent directories. `out_cid`, if not
+/// null, receives the new root CID to free with `wnfs_string_free`.
+#[no_mangle]
+pub extern "C" fn wnfs_write(
+    drive: *mut WnfsDrive<'static>,
+    path: *const c_char,
+    content: *const u8,
+    content_len: usize,
+    out_cid: *mut *mut c_char,
+) -> bool {
+    guard("wnfs_write", || {
+        let content = deserialize_bytes(content, content_len)?.to_vec();
+        let cid = drive_mut(drive)?.write(&deserialize_path(path)?, content)?;
+        serialize_cid(cid, out_cid)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_mkdir(drive: *mut WnfsDrive<'static>, path: *const c_char, out_cid: *mut *mut c_char) -> bool {
+    guard("wnfs_mkdir", || {
+        let cid = drive_mut(drive)?.mkdir(&deserialize_path(path)?)?;
+        serialize_cid(cid, out_cid)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_rm(drive: *mut WnfsDrive<'static>, path: *const c_char, out_cid: *mut *mut c_char) -> bool {
+    guard("wnfs_rm", || {
+        let cid = drive_mut(drive)?.rm(&deserialize_path(path)?)?;
+        serialize_cid(cid, out_cid)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_mv(
+    drive: *mut WnfsDrive<'static>,
+    source: *const c_char,
+    target: *const c_char,
+    out_cid: *mut *mut c_char,
+) -> bool {
+    guard("wnfs_mv", || {
+        let cid = drive_mut(drive)?.mv(&deserialize_path(source)?, &deserialize_path(target)?)?;
+        serialize_cid(cid, out_cid)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_cp(
+    drive: *mut WnfsDrive<'static>,
+    source: *const c_char,
+    target: *const c_char,
+    out_cid: *mut *mut c_char,
+) -> bool {
+    guard("wnfs_cp", || {
+        let cid = drive_mut(drive)?.cp(&deserialize_path(source)?, &deserialize_path(target)?)?;
+        serialize_cid(cid, out_cid)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_buffer_free(buffer: WnfsBuffer) {
+    free_box(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len));
+}
+
+#[no_mangle]
+pub extern "C" fn wnfs_string_free(text: *mut c_char) {
+    free_string(text);
+}
+
+/// Drops a value handed out through `Box::into_raw`. Null is ignored.
+fn free_box<T: ?Sized>(value: *mut T) {
+    if !value.is_null() {
+        drop(unsafe { Box::from_raw(value) });
+    }
+}
+
+fn free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(unsafe { CString::from_raw(text) });
+    }
+}
+
+/// Code of the last failure on this thread, `WNFS_ERROR_CODE_UNKNOWN` if none.
+#[no_mangle]
+pub extern "C" fn wnfs_last_error_code() -> ErrorCode {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ErrorCode::Unknown, |(code, _)| *code))
+}
+
+/// Message of the last failure on this thread, or null. Owned by the library
+/// and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn wnfs_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |(_, message)| message.as_ptr()))
+}
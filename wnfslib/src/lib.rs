@@ -1,3 +1,16 @@
+mod batch;
+mod cache;
+mod car;
+pub mod core;
+mod dedup;
+mod disk_cache;
+pub mod ffi;
+mod nodes;
+mod progress;
+mod transaction;
+mod transfer;
+mod upload_queue;
+
 // #[cfg(target_os = "android")]
 // #[allow(non_snake_case)]
 // #[allow(unused_imports)]
@@ -5,44 +18,95 @@
 pub mod android {
     extern crate jni;
 
-    use jni::objects::{JClass, JObject, JString, JValue};
-    use jni::signature::JavaType;
-    use jni::sys::{jbyteArray, jobject, jstring};
-    use jni::JNIEnv;
+    use jni::objects::{GlobalRef, JClass, JObject, JString, JValue};
+    use jni::signature::{JavaType, Primitive};
+    use jni::sys::{jboolean, jbooleanArray, jbyteArray, jint, jlong, jobject, jobjectArray, jstring, JNI_TRUE};
+    use jni::{JNIEnv, JavaVM};
     use libipld::Cid;
-    use wnfs::common::Metadata;
-    use log::{trace, Level};
-    extern crate android_logger;
+    use log::trace;
+    #[cfg(target_os = "android")]
     use android_logger::Config;
     use anyhow::Result;
-    use wnfsutils::blockstore::{FFIFriendlyBlockStore, FFIStore};
-    use wnfsutils::private_forest::PrivateDirectoryHelper;
+    use std::cell::{OnceCell, RefCell};
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use std::path::Path;
+    use std::time::Duration;
+    use std::sync::atomic::{AtomicI32, AtomicI64, Ordering};
+    use std::sync::{Arc, Mutex, OnceLock};
+    pub use crate::core::ErrorCode;
+    use crate::core::{export_car, BatchedStore, BlockBatch, BlockCache, BlockQuery, CacheStats, CachedStore, Cancel, DedupStore, DiskCache, DiskCachedStore, Entry, FFIStore, NodeInfo, NodeKind, Op, Progress, PendingUploads, PutPolicy, PutStats, QueuedStore, TransferReport, UploadOptions, UploadQueue, WnfsDrive};
 
 
     #[derive(Clone)]
     struct JNIStore<'a> {
         env: JNIEnv<'a>,
         fula_client: JObject<'a>,
+        /// Looked up on first use and shared by clones.
+        methods: Rc<OnceCell<OptionalMethods>>,
+    }
+
+    /// Which of the optional `Datastore` methods the Java object implements.
+    #[derive(Clone, Copy)]
+    struct OptionalMethods {
+        put_many: bool,
+        get_many: bool,
+        has: bool,
+        has_many: bool,
+    }
+
+    impl OptionalMethods {
+        fn of(env: JNIEnv, fula_client: JObject) -> Self {
+            Self {
+                put_many: has_method(env, fula_client, "putMany", "([[B[[B)[[B"),
+                get_many: has_method(env, fula_client, "getMany", "([[B)[[B"),
+                has: has_method(env, fula_client, "has", "([B)Z"),
+                has_many: has_method(env, fula_client, "hasMany", "([[B)[Z"),
+            }
+        }
+    }
+
+    fn has_method(env: JNIEnv, fula_client: JObject, name: &str, signature: &str) -> bool {
+        match env.get_method_id(fula_client, name, signature) {
+            Ok(_) => true,
+            Err(_) => {
+                // NoSuchMethodError: an implementation of the single-block interface only.
+                take_java_exception(env);
+                false
+            }
+        }
     }
 
     impl<'a> JNIStore<'a> {
         fn  new(env: JNIEnv<'a>, fula_client: JObject<'a>) -> Self {
-            Self { env: env, fula_client: fula_client }
+            Self { env: env, fula_client: fula_client, methods: Rc::default() }
+        }
+
+        /// A store for a `Datastore` whose optional methods are already known.
+        fn with_methods(env: JNIEnv<'a>, fula_client: JObject<'a>, methods: OptionalMethods) -> Self {
+            Self { env, fula_client, methods: Rc::new(OnceCell::from(methods)) }
+        }
+
+        fn methods(&self) -> OptionalMethods {
+            *self.methods.get_or_init(|| OptionalMethods::of(self.env, self.fula_client))
         }
     }
 
     impl<'a> FFIStore<'a> for JNIStore<'a> {
         /// Retrieves an array of bytes from the block store with given CID.
         fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
-            trace!("**********************get_block started**************");
             trace!("**********************get_block started**************");
             trace!("**********************get_block bytes={:?}", &cid);
             let get_fn = self
                 .env
                 .get_method_id(self.fula_client, "get", "([B)[B")
-                .unwrap();
+                .map_err(|err| {
+                    take_java_exception(self.env);
+                    err
+                })?;
 
-            let cid_jbyte_array = vec_to_jbyte_array(self.env, cid);
+            let missing_cid = Cid::try_from(cid.as_slice())?;
+            let cid_jbyte_array = vec_to_jbyte_array(self.env, cid).map_err(anyhow::Error::msg)?;
 
             let data_jbyte_array_res = self
                 .env
@@ -52,77 +116,647 @@ pub mod android {
                     JavaType::Object(String::from("[B")),
                     &[JValue::from(cid_jbyte_array)],
             );
-            if data_jbyte_array_res.is_ok() {
-                let data_jbyte_array_res_l_res = data_jbyte_array_res.ok().unwrap()
-                .l();
-                if data_jbyte_array_res_l_res.is_ok() {
-                    let data_jbyte_array = data_jbyte_array_res_l_res.unwrap();
-
-                    let data = jbyte_array_to_vec(self.env, data_jbyte_array.into_inner());
-                    trace!("**********************get_block finished**************");
-                    Ok(data)
-                } else {
-                    trace!("wnfsError get_block data_jbyte_array_res_l_res: {:?}", data_jbyte_array_res_l_res.err().unwrap().to_string());
-                    Ok(Vec::new())
+            let data_jbyte_array = match data_jbyte_array_res.and_then(|value| value.l()) {
+                Ok(data_jbyte_array) => data_jbyte_array,
+                Err(err) => {
+                    trace!("wnfsError get_block data_jbyte_array_res: {:?}", err.to_string());
+                    let exception = take_java_exception(self.env).unwrap_or_else(|| err.to_string());
+                    return Err(StoreError::GetFailed { cid: missing_cid, exception }.record());
                 }
-            } else {
-                trace!("wnfsError get_block data_jbyte_array_res: {:?}", data_jbyte_array_res.err().unwrap().to_string());
-                Ok(Vec::new())
+            };
+            if data_jbyte_array.is_null() {
+                trace!("wnfsError get_block returned null for {}", missing_cid);
+                return Err(StoreError::GetReturnedNull { cid: missing_cid }.record());
             }
 
+            let data = jbyte_array_to_vec(self.env, data_jbyte_array.into_inner()).map_err(anyhow::Error::msg)?;
+            trace!("**********************get_block finished**************");
+            Ok(data)
         }
 
         /// Stores an array of bytes in the block store.
         fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
             trace!("**********************put_block started**************");
             trace!("**********************put_block cid={:?}", &cid);
-            trace!("**********************put_block bytes={:?}", &bytes);
             let put_fn = self
                 .env
                 .get_method_id(self.fula_client, "put", "([B[B)[B")
-                .unwrap();
+                .map_err(|err| {
+                    take_java_exception(self.env);
+                    err
+                })?;
             trace!("**********************put_block put_fn done**************");
-            let data_jbyte_array = vec_to_jbyte_array(self.env, bytes);
-            trace!("**********************put_block data_jbyte_array done**************");
-            trace!(
-                "**********************put_block LVALUE_data_jbyte_array={:?}",
-                &JValue::from(data_jbyte_array)
-            );
-            let cid_jbyte_array = vec_to_jbyte_array(self.env, cid);
+            let block_cid = Cid::try_from(cid.as_slice())?;
+            let data_jbyte_array = vec_to_jbyte_array(self.env, bytes).map_err(anyhow::Error::msg)?;
+            let cid_jbyte_array = vec_to_jbyte_array(self.env, cid.clone()).map_err(anyhow::Error::msg)?;
             trace!("**********************put_block cid_jbyte_array done**************");
-            trace!(
-                "**********************put_block LVALUE_cid_jbyte_array={:?}",
-                &JValue::from(cid_jbyte_array)
-            );
 
-            let cid_jbyte_array = self
+            let cid_jbyte_array_res = self
                 .env
                 .call_method_unchecked(
                     self.fula_client,
                     put_fn,
                     JavaType::Object(String::from("[B")),
                     &[JValue::from(cid_jbyte_array), JValue::from(data_jbyte_array)],
-                )
-                .unwrap_or_else(|_err: jni::errors::Error| {
-                    trace!("**********************put_block first unwrap error**************");
-                    panic!("HERE1: {}", _err)
-                })
-                .l()
-                .unwrap_or_else(|_err: jni::errors::Error| {
-                    trace!("**********************put_block second unwrap error**************");
-                    panic!("HERE2: {}", _err)
-                });
+                );
+            let cid_jbyte_array = match cid_jbyte_array_res.and_then(|value| value.l()) {
+                Ok(cid_jbyte_array) => cid_jbyte_array,
+                Err(err) => {
+                    trace!("wnfsError put_block cid_jbyte_array_res: {:?}", err.to_string());
+                    let exception = take_java_exception(self.env).unwrap_or_else(|| err.to_string());
+                    return Err(StoreError::PutFailed { cid: block_cid, exception }.record());
+                }
+            };
             trace!("**********************put_block cid_jbyte_array done**************");
-            let _ = jbyte_array_to_vec(self.env, cid_jbyte_array.into_inner());
+            let returned_cid = if cid_jbyte_array.is_null() {
+                Vec::new()
+            } else {
+                jbyte_array_to_vec(self.env, cid_jbyte_array.into_inner()).map_err(anyhow::Error::msg)?
+            };
+            check_put_cid(&cid, &returned_cid)?;
             trace!("**********************put_block finished**************");
             Ok(())
 
         }
     }
 
+    impl<'a> BlockBatch<'a> for JNIStore<'a> {
+        /// Fetches the blocks with `Datastore.getMany` if the Java side has it.
+        fn get_blocks(&self, cids: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+            if !self.methods().get_many {
+                return cids.into_iter().map(|cid| self.get_block(cid)).collect();
+            }
+            let get_many_fn = self.env.get_method_id(self.fula_client, "getMany", "([[B)[[B").map_err(|err| {
+                take_java_exception(self.env);
+                err
+            })?;
+            trace!("**********************get_blocks started count={}**************", cids.len());
+            let cids_array = self.byte_arrays(&cids)?;
+            let blocks_res = self.env.call_method_unchecked(
+                self.fula_client,
+                get_many_fn,
+                JavaType::Object(String::from("[[B")),
+                &[JValue::from(JObject::from(cids_array))],
+            );
+            let blocks_array = match blocks_res.and_then(|value| value.l()) {
+                Ok(blocks_array) => blocks_array,
+                Err(err) => {
+                    trace!("wnfsError get_blocks blocks_res: {:?}", err.to_string());
+                    let exception = take_java_exception(self.env).unwrap_or_else(|| err.to_string());
+                    return Err(StoreError::GetManyFailed { blocks: cids.len(), exception }.record());
+                }
+            };
+            let blocks = self.vecs(blocks_array.into_inner(), cids.len())?;
+            cids.iter()
+                .zip(blocks)
+                .map(|(cid, block)| match block {
+                    Some(block) => Ok(block),
+                    None => Err(StoreError::GetReturnedNull { cid: Cid::try_from(cid.as_slice())? }.record()),
+                })
+                .collect()
+        }
+
+        /// Stores the blocks with `Datastore.putMany` if the Java side has it.
+        fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+            if !self.methods().put_many {
+                return blocks.into_iter().try_for_each(|(cid, bytes)| self.put_block(cid, bytes));
+            }
+            let put_many_fn = self.env.get_method_id(self.fula_client, "putMany", "([[B[[B)[[B").map_err(|err| {
+                take_java_exception(self.env);
+                err
+            })?;
+            trace!("**********************put_blocks started count={}**************", blocks.len());
+            let (cids, data): (Vec<_>, Vec<_>) = blocks.into_iter().unzip();
+            let cids_array = self.byte_arrays(&cids)?;
+            let data_array = self.byte_arrays(&data)?;
+            drop(data);
+            let returned_res = self.env.call_method_unchecked(
+                self.fula_client,
+                put_many_fn,
+                JavaType::Object(String::from("[[B")),
+                &[JValue::from(JObject::from(cids_array)), JValue::from(JObject::from(data_array))],
+            );
+            let returned_array = match returned_res.and_then(|value| value.l()) {
+                Ok(returned_array) => returned_array,
+                Err(err) => {
+                    trace!("wnfsError put_blocks returned_res: {:?}", err.to_string());
+                    let exception = take_java_exception(self.env).unwrap_or_else(|| err.to_string());
+                    return Err(StoreError::PutManyFailed { blocks: cids.len(), exception }.record());
+                }
+            };
+            let returned = self.vecs(returned_array.into_inner(), cids.len())?;
+            for (cid, returned_cid) in cids.iter().zip(returned) {
+                check_put_cid(cid, &returned_cid.unwrap_or_default())?;
+            }
+            trace!("**********************put_blocks finished**************");
+            Ok(())
+        }
+    }
+
+    impl<'a> BlockQuery<'a> for JNIStore<'a> {
+        /// Asks `Datastore.has` if the Java side has it. If the call fails the
+        /// answer is unknown and the block is put anyway.
+        fn has_block(&self, cid: &[u8]) -> Result<Option<bool>> {
+            if !self.methods().has {
+                return Ok(None);
+            }
+            let Ok(has_fn) = self.env.get_method_id(self.fula_client, "has", "([B)Z") else {
+                take_java_exception(self.env);
+                return Ok(None);
+            };
+            let cid_jbyte_array = vec_to_jbyte_array(self.env, cid.to_vec()).map_err(anyhow::Error::msg)?;
+            let has_res = self.env.call_method_unchecked(
+                self.fula_client,
+                has_fn,
+                JavaType::Primitive(Primitive::Boolean),
+                &[JValue::from(cid_jbyte_array)],
+            );
+            match has_res.and_then(|value| value.z()) {
+                Ok(has) => Ok(Some(has)),
+                Err(err) => {
+                    let exception = take_java_exception(self.env).unwrap_or_else(|| err.to_string());
+                    trace!("wnfsError has_block: {:?}", exception);
+                    Ok(None)
+                }
+            }
+        }
+
+        /// Asks `Datastore.hasMany` about the whole batch if the Java side has it. As with
+        /// `has_block`, a failed call leaves the answers unknown.
+        fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+            if !self.methods().has_many {
+                return cids.iter().map(|cid| self.has_block(cid)).collect();
+            }
+            let Ok(has_many_fn) = self.env.get_method_id(self.fula_client, "hasMany", "([[B)[Z") else {
+                take_java_exception(self.env);
+                return Ok(vec![None; cids.len()]);
+            };
+            trace!("**********************has_blocks started count={}**************", cids.len());
+            let cids_array = self.byte_arrays(cids)?;
+            let has_res = self.env.call_method_unchecked(
+                self.fula_client,
+                has_many_fn,
+                JavaType::Object(String::from("[Z")),
+                &[JValue::from(JObject::from(cids_array))],
+            );
+            let has_array = match has_res.and_then(|value| value.l()) {
+                Ok(has_array) if !has_array.is_null() => has_array.into_inner() as jbooleanArray,
+                Ok(_) => return Ok(vec![None; cids.len()]),
+                Err(err) => {
+                    let exception = take_java_exception(self.env).unwrap_or_else(|| err.to_string());
+                    trace!("wnfsError has_blocks: {:?}", exception);
+                    return Ok(vec![None; cids.len()]);
+                }
+            };
+            if self.env.get_array_length(has_array)? as usize != cids.len() {
+                trace!("wnfsError has_blocks: hasMany did not answer for {} blocks", cids.len());
+                return Ok(vec![None; cids.len()]);
+            }
+            let mut has = vec![0; cids.len()];
+            self.env.get_boolean_array_region(has_array, 0, &mut has)?;
+            Ok(has.into_iter().map(|has| Some(has == JNI_TRUE)).collect())
+        }
+    }
+
+    impl<'a> JNIStore<'a> {
+        /// Builds a Java `byte[][]`, releasing each element's local reference
+        /// as it goes so large batches stay within the local reference table.
+        fn byte_arrays(&self, items: &[Vec<u8>]) -> Result<jobjectArray> {
+            let array = self.env.new_object_array(items.len() as i32, "[B", JObject::null())?;
+            for (i, item) in items.iter().enumerate() {
+                let element = self.env.byte_array_from_slice(item)?;
+                self.env.set_object_array_element(array, i as i32, JObject::from(element))?;
+                self.env.delete_local_ref(JObject::from(element))?;
+            }
+            Ok(array)
+        }
+
+        /// Reads a Java `byte[][]` of `expected` elements; null elements are `None`.
+        fn vecs(&self, array: jobjectArray, expected: usize) -> Result<Vec<Option<Vec<u8>>>> {
+            if array.is_null() {
+                return Err(anyhow::anyhow!("store unavailable: Datastore returned no array for {} blocks", expected));
+            }
+            let length = self.env.get_array_length(array)? as usize;
+            if length != expected {
+                return Err(anyhow::anyhow!(
+                    "store mismatch: Datastore returned {} entries for {} blocks",
+                    length,
+                    expected
+                ));
+            }
+            (0..length)
+                .map(|i| {
+                    let element = self.env.get_object_array_element(array, i as i32)?;
+                    if element.is_null() {
+                        return Ok(None);
+                    }
+                    let bytes = self.env.convert_byte_array(element.into_inner())?;
+                    self.env.delete_local_ref(element)?;
+                    Ok(Some(bytes))
+                })
+                .collect()
+        }
+    }
+
+    /// Errors raised by the block store when the Java `Datastore` misbehaves.
+    #[derive(Debug)]
+    pub enum StoreError {
+        /// `Datastore.put` returned a CID other than the one the block was computed under,
+        /// or returned nothing at all.
+        Mismatch { expected: Cid, returned: Option<Cid> },
+        /// `Datastore.get` threw a Java exception for the block.
+        GetFailed { cid: Cid, exception: String },
+        /// `Datastore.get` returned null for the block.
+        GetReturnedNull { cid: Cid },
+        /// `Datastore.put` threw a Java exception for the block.
+        PutFailed { cid: Cid, exception: String },
+        /// `Datastore.getMany` threw a Java exception for a batch of blocks.
+        GetManyFailed { blocks: usize, exception: String },
+        /// `Datastore.putMany` threw a Java exception for a batch of blocks.
+        PutManyFailed { blocks: usize, exception: String },
+    }
+
+    impl std::fmt::Display for StoreError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                StoreError::Mismatch { expected, returned: Some(returned) } => write!(
+                    f,
+                    "store mismatch: Datastore.put stored block {} under {}",
+                    expected, returned
+                ),
+                StoreError::Mismatch { expected, returned: None } => write!(
+                    f,
+                    "store mismatch: Datastore.put returned no CID for block {}",
+                    expected
+                ),
+                StoreError::GetFailed { cid, exception } => write!(
+                    f,
+                    "block not available: Datastore.get threw for {}: {}",
+                    cid, exception
+                ),
+                StoreError::GetReturnedNull { cid } => write!(
+                    f,
+                    "block not available: Datastore.get returned null for {}",
+                    cid
+                ),
+                StoreError::PutFailed { cid, exception } => write!(
+                    f,
+                    "store unavailable: Datastore.put threw for {}: {}",
+                    cid, exception
+                ),
+                StoreError::GetManyFailed { blocks, exception } => write!(
+                    f,
+                    "block not available: Datastore.getMany threw for {} blocks: {}",
+                    blocks, exception
+                ),
+                StoreError::PutManyFailed { blocks, exception } => write!(
+                    f,
+                    "store unavailable: Datastore.putMany threw for {} blocks: {}",
+                    blocks, exception
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for StoreError {}
+
+    impl StoreError {
+        pub fn code(&self) -> ErrorCode {
+            match self {
+                StoreError::Mismatch { .. } => ErrorCode::StoreMismatch,
+                StoreError::GetFailed { .. }
+                | StoreError::GetReturnedNull { .. }
+                | StoreError::PutFailed { .. }
+                | StoreError::GetManyFailed { .. }
+                | StoreError::PutManyFailed { .. } => ErrorCode::StoreUnavailable,
+            }
+        }
+
+        /// Remembers this error for the current native call and converts it for the block store.
+        /// wnfs only hands the failure back to us as text, so the code is recovered from here
+        /// when the result object is built.
+        fn record(self) -> anyhow::Error {
+            LAST_STORE_ERROR.with(|last| *last.borrow_mut() = Some(self.code()));
+            self.into()
+        }
+    }
+
+    thread_local! {
+        static LAST_STORE_ERROR: RefCell<Option<ErrorCode>> = RefCell::new(None);
+    }
+
+    fn take_store_error() -> Option<ErrorCode> {
+        LAST_STORE_ERROR.with(|last| last.borrow_mut().take())
+    }
+
+    /// Runs a native call body with no store error on record, so a failure an earlier call left
+    /// behind on this thread is not reported as this call's.
+    fn catch_panic<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+        take_store_error();
+        crate::core::catch_panic(f)
+    }
+
+    /// Whether a failed put means the `Datastore` is unreachable, so that the blocks
+    /// go to the upload queue. The error is handled then, so its code is forgotten.
+    fn is_offline(err: &anyhow::Error) -> bool {
+        let offline = matches!(
+            err.downcast_ref::<StoreError>(),
+            Some(StoreError::PutFailed { .. } | StoreError::PutManyFailed { .. })
+        );
+        if offline {
+            take_store_error();
+        }
+        offline
+    }
+
+    /// Picks the code for a failed native call: a typed store error recorded during the call
+    /// wins over classification of the message text.
+    fn error_code(message: &str) -> ErrorCode {
+        take_store_error().unwrap_or_else(|| ErrorCode::from_message(message))
+    }
+
+    /// Clears a pending Java exception, if any, and returns its description.
+    fn take_java_exception(env: JNIEnv) -> Option<String> {
+        if !env.exception_check().unwrap_or(false) {
+            return None;
+        }
+        let throwable = env.exception_occurred().ok()?;
+        let _ = env.exception_clear();
+        let description = env
+            .call_method(throwable, "toString", "()Ljava/lang/String;", &[])
+            .and_then(|value| value.l())
+            .ok()
+            .and_then(|text| env.get_string(text.into()).ok())
+            .map(String::from);
+        let _ = env.exception_clear();
+        description.or_else(|| Some("unknown Java exception".to_owned()))
+    }
+
+    /// Compares the CID bytes returned by `Datastore.put` with the CID the block was put under.
+    fn check_put_cid(expected: &[u8], returned: &[u8]) -> Result<()> {
+        let expected = Cid::try_from(expected)?;
+        match Cid::try_from(returned) {
+            Ok(returned) if returned == expected => Ok(()),
+            Ok(returned) => Err(StoreError::Mismatch { expected, returned: Some(returned) }.record()),
+            Err(_) => Err(StoreError::Mismatch { expected, returned: None }.record()),
+        }
+    }
+
+    /// Block store used by sessions. Unlike `JNIStore` it does not borrow the
+    /// `JNIEnv` of a single native call, so it can outlive the call that opened
+    /// the session and attach to whichever thread is using it.
+    #[derive(Clone)]
+    struct SessionStore {
+        jvm: Arc<JavaVM>,
+        fula_client: GlobalRef,
+        methods: OptionalMethods,
+    }
+
+    impl SessionStore {
+        fn store<'e>(&self, env: JNIEnv<'e>) -> JNIStore<'e> {
+            JNIStore::with_methods(env, self.fula_client.as_obj(), self.methods)
+        }
+    }
+
+    impl FFIStore<'static> for SessionStore {
+        fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+            let env = self.jvm.attach_current_thread()?;
+            self.store(*env).get_block(cid)
+        }
+
+        fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+            let env = self.jvm.attach_current_thread()?;
+            self.store(*env).put_block(cid, bytes)
+        }
+    }
+
+    impl BlockBatch<'static> for SessionStore {
+        fn get_blocks(&self, cids: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+            let env = self.jvm.attach_current_thread()?;
+            self.store(*env).get_blocks(cids)
+        }
+
+        fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+            let env = self.jvm.attach_current_thread()?;
+            self.store(*env).put_blocks(blocks)
+        }
+    }
+
+    impl BlockQuery<'static> for SessionStore {
+        fn has_block(&self, cid: &[u8]) -> Result<Option<bool>> {
+            let env = self.jvm.attach_current_thread()?;
+            self.store(*env).has_block(cid)
+        }
+
+        fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+            let env = self.jvm.attach_current_thread()?;
+            self.store(*env).has_blocks(cids)
+        }
+    }
+
+    /// A loaded forest kept alive between native calls.
+    struct Session {
+        drive: WnfsDrive<'static>,
+        /// Blocks read or written through this session; empty if opened without a cache.
+        cache: Arc<Mutex<BlockCache>>,
+    }
+
+    // SAFETY: the drive is not `Send` only because of the `Rc`s inside it: the forest and
+    // root directory, and the pending blocks that the drive's block store and its batch share
+    // through their `BatchedStore` clones. Every clone of each of those lives in this session:
+    // `open_session` builds the drive from a store nothing else keeps, `WnfsDrive` hands back
+    // owned values only (CIDs, entries, bytes, reports), and `with_session` only lets `Send`
+    // values out. Moving a session therefore moves every reference to its `Rc`s along with it,
+    // and its mutex keeps two threads from using it at the same time. The `Datastore` is held
+    // as a `GlobalRef` and attached to whichever thread makes the call; the `JNIStore` built for
+    // that call is dropped before the call returns.
+    unsafe impl Send for Session {}
+
+    static NEXT_SESSION_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+    fn sessions() -> &'static Mutex<HashMap<jlong, Arc<Mutex<Session>>>> {
+        static SESSIONS: OnceLock<Mutex<HashMap<jlong, Arc<Mutex<Session>>>>> = OnceLock::new();
+        SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    fn open_session(
+        env: JNIEnv,
+        jni_fula_client: JObject,
+        forest_cid: Cid,
+        wnfs_key: Vec<u8>,
+        cache_bytes: usize,
+        write_back: bool,
+    ) -> Result<jlong, String> {
+        let jvm = env.get_java_vm().map_err(|e| e.to_string())?;
+        let fula_client = env.new_global_ref(jni_fula_client).map_err(|e| e.to_string())?;
+        let cache = Arc::new(Mutex::new(BlockCache::new(cache_bytes)));
+        let methods = OptionalMethods::of(env, jni_fula_client);
+        let store = DiskCachedStore::new(SessionStore { jvm: Arc::new(jvm), fula_client, methods }, disk_cache());
+        let store = deduplicated(QueuedStore::new(store, upload_queue(), is_offline));
+        let store = if write_back { BatchedStore::write_back(store) } else { BatchedStore::new(store) };
+        let cached = CachedStore::new(store.clone(), cache.clone());
+        let drive = WnfsDrive::load(cached, forest_cid, wnfs_key)?.with_batch(store);
+        drive.send_pending()?;
+
+        let handle = NEXT_SESSION_HANDLE.fetch_add(1, Ordering::SeqCst);
+        sessions()
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(handle, Arc::new(Mutex::new(Session { drive, cache })));
+        Ok(handle)
+    }
+
+    fn close_session(handle: jlong) -> Result<(), String> {
+        let removed = sessions().lock().map_err(|e| e.to_string())?.remove(&handle);
+        match removed {
+            Some(_) => Ok(()),
+            None => Err(format!("invalid session handle {}", handle)),
+        }
+    }
+
+    /// Runs `f` against the drive of an open session. The registry lock is
+    /// released before `f` runs so that different sessions do not block each other.
+    /// `T: Send` keeps `f` from returning anything that shares the drive's `Rc`s.
+    fn with_session<T: Send>(
+        handle: jlong,
+        f: impl FnOnce(&mut WnfsDrive<'static>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let session = find_session(handle)?;
+        let mut session = session.lock().map_err(|e| e.to_string())?;
+        f(&mut session.drive)
+    }
+
+    fn find_session(handle: jlong) -> Result<Arc<Mutex<Session>>, String> {
+        sessions()
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(&handle)
+            .cloned()
+            .ok_or_else(|| format!("invalid session handle {}", handle))
+    }
+
+    fn session_cache_stats(handle: jlong) -> Result<CacheStats, String> {
+        let cache = find_session(handle)?.lock().map_err(|e| e.to_string())?.cache.clone();
+        let stats = cache.lock().map_err(|e| e.to_string())?.stats();
+        Ok(stats)
+    }
+
+
+    /// Adapts an optional Java `ProgressListener` into a `Progress`. An exception
+    /// thrown by the listener is logged and cleared so it cannot abort the transfer.
+    fn progress_listener<'a>(env: JNIEnv<'a>, jni_listener: JObject<'a>, jni_interval_ms: jlong) -> Progress<'a> {
+        if jni_listener.is_null() {
+            return Progress::none();
+        }
+        let interval = Duration::from_millis(u64::try_from(jni_interval_ms).unwrap_or(0));
+        Progress::new(
+            move |processed, total| {
+                let args = [JValue::Long(processed as i64), JValue::Long(total as i64)];
+                if env.call_method(jni_listener, "onProgress", "(JJ)V", &args).is_err() {
+                    let exception = take_java_exception(env);
+                    trace!("wnfsError ProgressListener.onProgress failed: {:?}", exception);
+                }
+            },
+            interval,
+        )
+    }
+
+    /// Adapts an optional Java `CancellationToken` into a `Cancel`. If the token
+    /// cannot be queried the transfer is treated as cancelled.
+    fn cancellation_token<'a>(env: JNIEnv<'a>, jni_cancel: JObject<'a>) -> Cancel<'a> {
+        if jni_cancel.is_null() {
+            return Cancel::none();
+        }
+        Cancel::new(move || match env.call_method(jni_cancel, "isCancelled", "()Z", &[]).and_then(|v| v.z()) {
+            Ok(cancelled) => cancelled,
+            Err(_) => {
+                let exception = take_java_exception(env);
+                trace!("wnfsError CancellationToken.isCancelled failed: {:?}", exception);
+                true
+            }
+        })
+    }
+
+    static DISK_CACHE: Mutex<Option<Arc<DiskCache>>> = Mutex::new(None);
+
+    /// The persistent block cache, if the app has enabled one.
+    fn disk_cache() -> Option<Arc<DiskCache>> {
+        DISK_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Opens `dir` as the persistent block cache used by every later call,
+    /// replacing the current one. Sessions already open keep the one they started with.
+    fn enable_disk_cache(dir: &Path, capacity: u64, key: &[u8]) -> Result<(), String> {
+        let mut current = DISK_CACHE.lock().map_err(|e| e.to_string())?;
+        // Close the old cache first so the same directory can be reopened.
+        *current = None;
+        *current = Some(Arc::new(DiskCache::open(dir, capacity, key)?));
+        Ok(())
+    }
+
+    static UPLOAD_QUEUE: Mutex<Option<Arc<UploadQueue>>> = Mutex::new(None);
+
+    /// The queue of blocks waiting for the `Datastore`, if the app has enabled one.
+    fn upload_queue() -> Option<Arc<UploadQueue>> {
+        UPLOAD_QUEUE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Opens `dir` as the upload queue used by every later call, replacing the
+    /// current one. Sessions already open keep the one they started with.
+    fn enable_upload_queue(dir: &Path) -> Result<(), String> {
+        let mut current = UPLOAD_QUEUE.lock().map_err(|e| e.to_string())?;
+        // Close the old queue first so the same directory can be reopened.
+        *current = None;
+        *current = Some(Arc::new(UploadQueue::open(dir)?));
+        Ok(())
+    }
+
+    static PUT_POLICY: AtomicI32 = AtomicI32::new(PutPolicy::Always as i32);
+
+    fn put_policy() -> PutPolicy {
+        PutPolicy::from_i32(PUT_POLICY.load(Ordering::SeqCst)).unwrap_or(PutPolicy::Always)
+    }
+
+    /// Puts and skipped puts of every store since the library was loaded.
+    fn put_stats() -> Arc<PutStats> {
+        static PUT_STATS: OnceLock<Arc<PutStats>> = OnceLock::new();
+        PUT_STATS.get_or_init(Arc::default).clone()
+    }
+
+    /// The Java `Datastore` behind the persistent block cache and the upload queue,
+    /// if they are enabled, with writes collected into batches for `putMany` and the
+    /// current `PutPolicy` applied to each batch.
+    fn jni_store<'a>(
+        env: JNIEnv<'a>,
+        jni_fula_client: JObject<'a>,
+    ) -> BatchedStore<DedupStore<QueuedStore<DiskCachedStore<JNIStore<'a>>>>> {
+        let store = DiskCachedStore::new(JNIStore::new(env, jni_fula_client), disk_cache());
+        BatchedStore::new(deduplicated(QueuedStore::new(store, upload_queue(), is_offline)))
+    }
+
+    /// Applies the current `PutPolicy` to the blocks sent to `store`.
+    fn deduplicated<S>(store: S) -> DedupStore<S> {
+        DedupStore::new(store, put_policy(), disk_cache(), put_stats())
+    }
+
+    /// Reloads the forest at `jni_cid` through the Java `Datastore`.
+    fn reload_drive<'a>(
+        env: JNIEnv<'a>,
+        jni_fula_client: JObject<'a>,
+        jni_cid: JString,
+    ) -> Result<WnfsDrive<'a>, String> {
+        let store = jni_store(env, jni_fula_client);
+        let cid = deserialize_cid(env, jni_cid)?;
+        Ok(WnfsDrive::reload(store.clone(), cid)?.with_batch(store))
+    }
+
     #[no_mangle]
     pub extern "C" fn Java_land_fx_wnfslib_Fs_initRustLogger(_: JNIEnv, _: JClass) {
-        android_logger::init_once(Config::default().with_min_level(Level::Trace));
+        #[cfg(target_os = "android")]
+        android_logger::init_once(Config::default().with_min_level(log::Level::Trace));
+        // Host builds (the JVM tests under tests/) log to stderr, filtered by RUST_LOG.
+        #[cfg(not(target_os = "android"))]
+        let _ = env_logger::try_init();
     }
 
 
@@ -136,556 +770,988 @@ pub mod android {
         jni_cid: JString,
     ) -> jobject  {
         trace!("**********************loadWithWNFSKeyNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let wnfs_key: Vec<u8> = jbyte_array_to_vec(env, jni_wnfs_key);
-        let forest_cid = deserialize_cid(env, jni_cid);
-        let helper_res = PrivateDirectoryHelper::synced_load_with_wnfs_key(block_store, forest_cid, wnfs_key);
+        let result = catch_panic(|| {
+            let store = jni_store(env, jni_fula_client);
+            let wnfs_key: Vec<u8> = jbyte_array_to_vec(env, jni_wnfs_key)?;
+            let forest_cid = deserialize_cid(env, jni_cid)?;
+            WnfsDrive::load(store.clone(), forest_cid, wnfs_key)?.with_batch(store).send_pending()
+        });
         trace!("**********************loadWithWNFSKeyNative finished**************");
-        if helper_res.is_ok() {
-            unsafe {
-            serialize_result(env, None)
+        match result {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_loadWithWNFSKeyNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
             }
-        } else {
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_loadWithWNFSKeyNative: {:?}", msg);
-            unsafe {
-                serialize_result(env, Some(msg))
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_initNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_wnfs_key: jbyteArray,
+    ) -> jobject {
+        trace!("**********************wnfsInfo createRootDirNative started**************");
+        let result = catch_panic(|| {
+            let store = jni_store(env, jni_fula_client);
+            let wnfs_key: Vec<u8> = jbyte_array_to_vec(env, jni_wnfs_key)?;
+            let (drive, cid) = WnfsDrive::init(store.clone(), wnfs_key)?;
+            drive.with_batch(store).send_pending()?;
+            Ok(cid)
+        });
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_initNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeFileFromPathNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+        jni_filename: JString,
+    ) -> jobject {
+        trace!("**********************writeFileFromPathNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let filename = deserialize_filename(env, jni_filename)?;
+            drive.write_from_path(&path_segments, &filename)
+        });
+        trace!("**********************writeFileFromPathNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileFromPathNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeFileStreamFromPathNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+        jni_filename: JString,
+        jni_listener: JObject,
+        jni_interval_ms: jlong,
+        jni_cancel: JObject,
+    ) -> jobject {
+        trace!("**********************writeFileStreamFromPathNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let filename = deserialize_filename(env, jni_filename)?;
+            let mut progress = progress_listener(env, jni_listener, jni_interval_ms);
+            let cancel = cancellation_token(env, jni_cancel);
+            drive.write_stream_from_path(&path_segments, &filename, &mut progress, &cancel)
+        });
+        trace!("**********************writeFileStreamFromPathNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileStreamFromPathNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeDirFromPathNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+        jni_local_dir: JString,
+        jni_skip_hidden: jboolean,
+        jni_overwrite: jboolean,
+    ) -> jobject {
+        trace!("**********************writeDirFromPathNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let local_dir = deserialize_filename(env, jni_local_dir)?;
+            let options = UploadOptions {
+                skip_hidden: jni_skip_hidden == JNI_TRUE,
+                overwrite: jni_overwrite == JNI_TRUE,
+            };
+            let report = drive.write_dir_from_path(
+                &path_segments,
+                Path::new(&local_dir),
+                &options,
+                &|msg| error_code(msg) as i32,
+            )?;
+            prepare_transfer_output(report)
+        });
+        trace!("**********************writeDirFromPathNative finished**************");
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeDirFromPathNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFilestreamToPathNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+        jni_filename: JString,
+        jni_offset: jlong,
+        jni_listener: JObject,
+        jni_interval_ms: jlong,
+        jni_cancel: JObject,
+    ) -> jstring {
+        trace!("wnfs11 **********************readFilestreamToPathNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let filename = deserialize_filename(env, jni_filename)?;
+            let offset = deserialize_offset(jni_offset)?;
+            trace!("wnfs11 **********************readFilestreamToPathNative filename created**************");
+            let mut progress = progress_listener(env, jni_listener, jni_interval_ms);
+            let cancel = cancellation_token(env, jni_cancel);
+            drive.read_stream_to_path(&path_segments, &filename, offset, &mut progress, &cancel)?;
+            Ok(filename)
+        });
+        trace!("wnfs11 **********************readFilestreamToPathNative finished**************");
+        match result {
+            Ok(filename) => unsafe { serialize_string_result(env, None, Some(filename)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFilestreamToPathNative: {:?}", msg);
+                unsafe { serialize_string_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_readDirToPathNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+        jni_local_dir: JString,
+    ) -> jobject {
+        trace!("**********************readDirToPathNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let local_dir = deserialize_filename(env, jni_local_dir)?;
+            let report = drive.read_dir_to_path(
+                &path_segments,
+                Path::new(&local_dir),
+                &|msg| error_code(msg) as i32,
+            )?;
+            prepare_transfer_output(report)
+        });
+        trace!("**********************readDirToPathNative finished**************");
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_readDirToPathNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_exportCarNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+        jni_output_path: JString,
+        jni_subtree_path: JString,
+    ) -> jobject {
+        trace!("**********************exportCarNative started**************");
+        let result = catch_panic(|| {
+            let cid = deserialize_cid(env, jni_cid)?;
+            let output_path = deserialize_filename(env, jni_output_path)?;
+            let subtree = if jni_subtree_path.is_null() {
+                None
+            } else {
+                Some(prepare_path_segments(env, jni_subtree_path)?)
+            };
+            // Blocks still waiting in the upload queue belong in the export too.
+            let store = DiskCachedStore::new(JNIStore::new(env, jni_fula_client), disk_cache());
+            let store = QueuedStore::new(store, upload_queue(), is_offline);
+            let blocks = export_car(store, cid, Path::new(&output_path), subtree.as_deref())?;
+            Ok(blocks as jlong)
+        });
+        trace!("**********************exportCarNative finished**************");
+        match result {
+            Ok(blocks) => unsafe { serialize_long_result(env, None, Some(blocks)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_exportCarNative: {:?}", msg);
+                unsafe { serialize_long_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFileToPathNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+        jni_path_segments: JString,
+        jni_filename: JString,
+    ) -> jstring {
+        trace!("wnfs11 **********************readFileToPathNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let filename = deserialize_filename(env, jni_filename)?;
+            trace!("wnfs11 **********************readFileToPathNative filename created**************");
+            drive.read_to_path(&path_segments, &filename)?;
+            Ok(filename)
+        });
+        trace!("wnfs11 **********************readFileToPathNative finished**************");
+        match result {
+            Ok(filename) => unsafe { serialize_string_result(env, None, Some(filename)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFileToPathNative: {:?}", msg);
+                unsafe { serialize_string_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeFileNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+        jni_content: jbyteArray,
+    ) -> jobject {
+        trace!("**********************writeFileNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let content = jbyte_array_to_vec(env, jni_content)?;
+            drive.write(&path_segments, content)
+        });
+        trace!("**********************writeFileNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFileNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+    ) -> jbyteArray {
+        trace!("**********************readFileNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            drive.read(&path_segments)
+        });
+        trace!("**********************readFileNative finished**************");
+        match result {
+            Ok(content) => unsafe { serialize_bytes_result(env, None, Some(content)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFileNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFileRangeNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+        jni_offset: jlong,
+        jni_length: jlong,
+    ) -> jbyteArray {
+        trace!("**********************readFileRangeNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let offset = deserialize_offset(jni_offset)?;
+            let length = usize::try_from(jni_length)
+                .map_err(|_| format!("invalid range: length {} is negative", jni_length))?;
+            drive.read_range(&path_segments, offset, Some(length))
+        });
+        trace!("**********************readFileRangeNative finished**************");
+        match result {
+            Ok(content) => unsafe { serialize_bytes_result(env, None, Some(content)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFileRangeNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_mkdirNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************mkDirNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            drive.mkdir(&path_segments)
+        });
+        trace!("**********************mkDirNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_mkdirNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_mvNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_source_path_segments: JString,
+        jni_target_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************mvNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let source_path_segments = prepare_path_segments(env, jni_source_path_segments)?;
+            let target_path_segments = prepare_path_segments(env, jni_target_path_segments)?;
+            drive.mv(&source_path_segments, &target_path_segments)
+        });
+        trace!("**********************mvNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_mvNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_cpNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_fula_client: JObject,
+        jni_cid: JString,
+
+        jni_source_path_segments: JString,
+        jni_target_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************cpNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let source_path_segments = prepare_path_segments(env, jni_source_path_segments)?;
+            let target_path_segments = prepare_path_segments(env, jni_target_path_segments)?;
+            drive.cp(&source_path_segments, &target_path_segments)
+        });
+        trace!("**********************cpNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_cpNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
             }
         }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_initNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_commitTransactionNative(
         env: JNIEnv,
         _: JClass,
         jni_fula_client: JObject,
-        jni_wnfs_key: jbyteArray,
+        jni_cid: JString,
+
+        jni_kinds: jobjectArray,
+        jni_paths: jobjectArray,
+        jni_targets: jobjectArray,
+        jni_contents: jobjectArray,
     ) -> jobject {
-        trace!("**********************wnfsInfo createRootDirNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        trace!("wnfsInfo Java_land_fx_wnfslib_Fs_initNative store created");
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        trace!("wnfsInfo Java_land_fx_wnfslib_Fs_initNative block_store created");
-        let wnfs_key: Vec<u8> = jbyte_array_to_vec(env, jni_wnfs_key);
-        trace!("wnfsInfo Java_land_fx_wnfslib_Fs_initNative wnfs_key created");
-        let helper_res = PrivateDirectoryHelper::synced_init(block_store, wnfs_key);
-        trace!("wnfsInfo Java_land_fx_wnfslib_Fs_initNative helper_res created");
-        if helper_res.is_ok() {
-            let (_, _, cid) = helper_res.unwrap();
-            trace!("wnfsInfo Java_land_fx_wnfslib_Fs_initNative helper_res ok");
-            unsafe {
-            serialize_config_result(env, None, Some(cid))
-            }
-        } else {
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_initNative: {:?}", msg.to_owned());
-            unsafe {
-                serialize_config_result(env, Some(msg.to_owned()), None)
+        trace!("**********************commitTransactionNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let ops = prepare_transaction_ops(env, jni_kinds, jni_paths, jni_targets, jni_contents)?;
+            drive.apply(ops)
+        });
+        trace!("**********************commitTransactionNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_commitTransactionNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
             }
         }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeFileFromPathNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_rmNative(
         env: JNIEnv,
         _: JClass,
         jni_fula_client: JObject,
         jni_cid: JString,
 
         jni_path_segments: JString,
-        jni_filename: JString,
     ) -> jobject {
-        trace!("**********************writeFileFromPathNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res = PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper =&mut helper_res.ok().unwrap();
-                let path_segments = prepare_path_segments(env, jni_path_segments);
-
-                let filename: String = env
-                    .get_string(jni_filename)
-                    .expect("Failed to parse input path segments")
-                    .into();
-
-                let write_file_result =
-                    helper.synced_write_file_from_path(&path_segments, &filename);
-                    trace!("**********************writeFileFromPathNative finished**************");
-                if write_file_result.is_ok() {
-                    let cid = write_file_result.ok().unwrap();
-                    unsafe{
-                        return serialize_config_result(env, None, Some(cid));
-                    }
-                } else {
-                    let msg = write_file_result.err().unwrap();
-                    trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileFromPathNative: {:?}", msg);
-                    unsafe{
-                        return serialize_config_result(env, Some(msg), None);
-                    }
-                }
-
-        } else {
-            let msg = &mut helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileFromPathNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_config_result(env, Some(msg.to_owned()), None);
+        trace!("**********************rmNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            drive.rm(&path_segments)
+        });
+        trace!("**********************rmNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_rmNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
             }
         }
-
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeFileStreamFromPathNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_lsNative(
         env: JNIEnv,
         _: JClass,
         jni_fula_client: JObject,
         jni_cid: JString,
 
         jni_path_segments: JString,
-        jni_filename: JString,
-    ) -> jobject {
-        trace!("**********************writeFileStreamFromPathNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res = PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper =&mut helper_res.ok().unwrap();
-                let path_segments = prepare_path_segments(env, jni_path_segments);
-
-                let filename: String = env
-                    .get_string(jni_filename)
-                    .expect("Failed to parse input path segments")
-                    .into();
-
-                let write_file_result =
-                    helper.synced_write_file_stream_from_path(&path_segments, &filename);
-                    trace!("**********************writeFileStreamFromPathNative finished**************");
-                if write_file_result.is_ok() {
-                    let cid = write_file_result.ok().unwrap();
-                    unsafe{
-                        return serialize_config_result(env, None, Some(cid));
-                    }
-                } else {
-                    let msg = write_file_result.err().unwrap();
-                    trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileStreamFromPathNative: {:?}", msg);
-                    unsafe{
-                        return serialize_config_result(env, Some(msg), None);
-                    }
-                }
-
-        } else {
-            let msg = &mut helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileStreamFromPathNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_config_result(env, Some(msg.to_owned()), None);
+    ) -> jbyteArray {
+        trace!("**********************lsNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let entries = drive.ls(&path_segments)?;
+            prepare_ls_output(entries)
+        });
+        trace!("**********************lsNative finished**************");
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_lsNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
             }
         }
-
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFilestreamToPathNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_statNative(
         env: JNIEnv,
         _: JClass,
         jni_fula_client: JObject,
         jni_cid: JString,
 
         jni_path_segments: JString,
-        jni_filename: JString,
-    ) -> jstring {
-        trace!("wnfs11 **********************readFilestreamToPathNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res = PrivateDirectoryHelper::synced_reload(block_store, cid);
-        if helper_res.is_ok(){
-            let helper =&mut helper_res.ok().unwrap();
-            let path_segments = prepare_path_segments(env, jni_path_segments);
-            let filename: String = env
-                .get_string(jni_filename)
-                .expect("Failed to parse input path segments")
-                .into();
-            trace!("wnfs11 **********************readFilestreamToPathNative filename created**************");
-            let result = helper.synced_read_filestream_to_path(&filename, &path_segments, 0);
-            trace!("wnfs11 **********************readFilestreamToPathNative finished**************");
-            if result.is_ok() {
-                unsafe{
-                    return serialize_string_result(env, None, Some(filename));
-                }
-            } else {
-                let err = result.err().unwrap();
-                trace!("wnfsError occured in Java_land_fx_wnfslib_Fs_readFilestreamToPathNative on result: {:?}", err.to_owned());
-                unsafe{
-                    return serialize_string_result(env,  Some(err.to_owned()), None);
-                }
-            }
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFilestreamToPathNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_string_result(env, Some(msg.to_owned()), None);
+    ) -> jobject {
+        trace!("**********************statNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let info = drive.stat(&path_segments)?;
+            prepare_stat_output(info)
+        });
+        trace!("**********************statNative finished**************");
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_statNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
             }
         }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFileToPathNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_existsNative(
         env: JNIEnv,
         _: JClass,
         jni_fula_client: JObject,
         jni_cid: JString,
+
         jni_path_segments: JString,
-        jni_filename: JString,
-    ) -> jstring {
-        trace!("wnfs11 **********************readFileToPathNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-            let path_segments = prepare_path_segments(env, jni_path_segments);
-            let filename: String = env
-                .get_string(jni_filename)
-                .expect("Failed to parse input path segments")
-                .into();
-            trace!("wnfs11 **********************readFileToPathNative filename created**************");
-            let result = helper.synced_read_file_to_path(&path_segments, &filename);
-            trace!("wnfs11 **********************readFileToPathNative finished**************");
-            if result.is_ok() {
-                unsafe{
-                    return serialize_string_result(env, None, Some(filename));
-                }
-            } else {
-                let err = result.err().unwrap();
-                trace!("wnfsError occured in Java_land_fx_wnfslib_Fs_readFileToPathNative on result: {:?}", err.to_owned());
-                unsafe{
-                    return serialize_string_result(env,  Some(err.to_owned()), None);
-                }
+    ) -> jobject {
+        trace!("**********************existsNative started**************");
+        let result = catch_panic(|| {
+            let drive = &mut reload_drive(env, jni_fula_client, jni_cid)?;
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            drive.exists(&path_segments)
+        });
+        trace!("**********************existsNative finished**************");
+        match result {
+            Ok(kind) => {
+                let kind = match kind {
+                    Some(NodeKind::File) => "file",
+                    Some(NodeKind::Directory) => "directory",
+                    None => "none",
+                };
+                unsafe { serialize_string_result(env, None, Some(kind.to_string())) }
             }
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFileToPathNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_string_result(env, Some(msg.to_owned()), None);
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_existsNative: {:?}", msg);
+                unsafe { serialize_string_result(env, Some(msg), None) }
             }
         }
-
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeFileNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_openSessionNative(
         env: JNIEnv,
         _: JClass,
         jni_fula_client: JObject,
         jni_cid: JString,
-
-        jni_path_segments: JString,
-        jni_content: jbyteArray,
+        jni_wnfs_key: jbyteArray,
+        jni_cache_bytes: jlong,
+        jni_write_back: jboolean,
     ) -> jobject {
-        trace!("**********************writeFileNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-            let path_segments = prepare_path_segments(env, jni_path_segments);
-            let content = jbyte_array_to_vec(env, jni_content);
-
-            let write_file_res =
-                helper.synced_write_file(&path_segments, content, 0);
-            trace!("**********************writeFileNative finished**************");
-            if write_file_res.is_ok() {
-                let cid = write_file_res.ok().unwrap();
-                unsafe{
-                    let config: jobject = serialize_config_result(env, None, Some(cid));
-                    return config;
-                }
-            } else {
-                let msg = write_file_res
-                    .err()
-                    .unwrap();
-                trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileNative: {:?}", msg);
-                unsafe{
-                    return serialize_config_result(env,  Some(msg.to_owned()), None);
-                }
-            }
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_config_result(env, Some(msg.to_owned()), None);
+        trace!("**********************openSessionNative started**************");
+        let result = catch_panic(|| {
+            let cid = deserialize_cid(env, jni_cid)?;
+            let wnfs_key: Vec<u8> = jbyte_array_to_vec(env, jni_wnfs_key)?;
+            let cache_bytes = deserialize_cache_bytes(jni_cache_bytes)?;
+            open_session(env, jni_fula_client, cid, wnfs_key, cache_bytes, jni_write_back == JNI_TRUE)
+        });
+        trace!("**********************openSessionNative finished**************");
+        match result {
+            Ok(handle) => unsafe { serialize_long_result(env, None, Some(handle)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_openSessionNative: {:?}", msg);
+                unsafe { serialize_long_result(env, Some(msg), None) }
             }
         }
+    }
 
-
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_cacheStatsSessionNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_handle: jlong,
+    ) -> jobject {
+        trace!("**********************cacheStatsSessionNative started**************");
+        let result = catch_panic(|| {
+            let stats = session_cache_stats(jni_handle)?;
+            serde_json::to_vec(&stats).map_err(|err| err.to_string())
+        });
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_cacheStatsSessionNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
+            }
+        }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFileNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_enableDiskCacheNative(
         env: JNIEnv,
         _: JClass,
-        jni_fula_client: JObject,
-        jni_cid: JString,
+        jni_directory: JString,
+        jni_max_bytes: jlong,
+        jni_key: jbyteArray,
+    ) -> jobject {
+        trace!("**********************enableDiskCacheNative started**************");
+        let result = catch_panic(|| {
+            let directory = deserialize_filename(env, jni_directory)?;
+            let max_bytes = deserialize_cache_bytes(jni_max_bytes)?;
+            let key = jbyte_array_to_vec(env, jni_key)?;
+            enable_disk_cache(Path::new(&directory), max_bytes as u64, &key)
+        });
+        match result {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_enableDiskCacheNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
+            }
+        }
+    }
 
-        jni_path_segments: JString,
-    ) -> jbyteArray {
-        trace!("**********************readFileNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-            let path_segments = prepare_path_segments(env, jni_path_segments);
-            trace!("**********************readFileNative finished**************");
-            let result = helper.synced_read_file(&path_segments);
-            if result.is_ok() {
-                unsafe{
-                    return serialize_bytes_result(env, None, Some(result.ok().unwrap()));
-                }
-            }else{
-                let msg = result
-                    .err()
-                    .unwrap();
-                trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFileNative: {:?}", msg);
-                unsafe{
-                    return serialize_bytes_result(env,  Some(msg.to_owned()), None);
-                }
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_disableDiskCacheNative(env: JNIEnv, _: JClass) -> jobject {
+        trace!("**********************disableDiskCacheNative started**************");
+        let result = catch_panic(|| {
+            *DISK_CACHE.lock().map_err(|e| e.to_string())? = None;
+            Ok(())
+        });
+        match result {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_disableDiskCacheNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_clearCacheNative(env: JNIEnv, _: JClass) -> jobject {
+        trace!("**********************clearCacheNative started**************");
+        let result = catch_panic(|| match disk_cache() {
+            Some(cache) => cache.clear(),
+            None => Ok(()),
+        });
+        match result {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_clearCacheNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_diskCacheStatsNative(env: JNIEnv, _: JClass) -> jobject {
+        trace!("**********************diskCacheStatsNative started**************");
+        let result = catch_panic(|| {
+            let stats = match disk_cache() {
+                Some(cache) => cache.stats(),
+                None => BlockCache::new(0).stats(),
+            };
+            serde_json::to_vec(&stats).map_err(|err| err.to_string())
+        });
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_diskCacheStatsNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
             }
+        }
+    }
 
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFileNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_bytes_result(env, Some(msg.to_owned()), None);
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_setPutPolicyNative(env: JNIEnv, _: JClass, jni_policy: jint) -> jobject {
+        trace!("**********************setPutPolicyNative started**************");
+        let result = catch_panic(|| {
+            let policy = deserialize_put_policy(jni_policy)?;
+            PUT_POLICY.store(policy as i32, Ordering::SeqCst);
+            Ok(())
+        });
+        match result {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_setPutPolicyNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
             }
         }
+    }
 
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_putStatsNative(env: JNIEnv, _: JClass) -> jobject {
+        trace!("**********************putStatsNative started**************");
+        let result = catch_panic(|| serde_json::to_vec(&put_stats().counters()).map_err(|err| err.to_string()));
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_putStatsNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
+            }
+        }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_mkdirNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_enableUploadQueueNative(
         env: JNIEnv,
         _: JClass,
-        jni_fula_client: JObject,
-        jni_cid: JString,
-
-        jni_path_segments: JString,
+        jni_directory: JString,
     ) -> jobject {
-        trace!("**********************mkDirNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-            let path_segments = prepare_path_segments(env, jni_path_segments);
-            let mkdir_res = helper.synced_mkdir(&path_segments);
-            if mkdir_res.is_ok() {
-                let cid = mkdir_res.ok().unwrap();
-                trace!("**********************mkDirNative finished**************");
-                unsafe{return serialize_config_result(env, None, Some(cid))}
-            } else {
-                let msg = mkdir_res
-                    .err()
-                    .unwrap();
-                trace!("wnfsError in Java_land_fx_wnfslib_Fs_mkdirNative: {:?}", msg.to_owned());
-                unsafe{return serialize_config_result(env, Some(msg.to_owned()), None);}
+        trace!("**********************enableUploadQueueNative started**************");
+        let result = catch_panic(|| {
+            let directory = deserialize_filename(env, jni_directory)?;
+            enable_upload_queue(Path::new(&directory))
+        });
+        match result {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_enableUploadQueueNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
+            }
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_disableUploadQueueNative(env: JNIEnv, _: JClass) -> jobject {
+        trace!("**********************disableUploadQueueNative started**************");
+        let result = catch_panic(|| {
+            *UPLOAD_QUEUE.lock().map_err(|e| e.to_string())? = None;
+            Ok(())
+        });
+        match result {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_disableUploadQueueNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
             }
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_mkDirNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_config_result(env, Some(msg.to_owned()), None);
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_pendingUploadsNative(env: JNIEnv, _: JClass) -> jobject {
+        trace!("**********************pendingUploadsNative started**************");
+        let result = catch_panic(|| {
+            let pending = match upload_queue() {
+                Some(queue) => queue.pending(),
+                None => PendingUploads { blocks: 0, bytes: 0 },
+            };
+            serde_json::to_vec(&pending).map_err(|err| err.to_string())
+        });
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_pendingUploadsNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
             }
         }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_mvNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_syncPendingNative(
         env: JNIEnv,
         _: JClass,
         jni_fula_client: JObject,
-        jni_cid: JString,
-
-        jni_source_path_segments: JString,
-        jni_target_path_segments: JString,
     ) -> jobject {
-        trace!("**********************mvNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-            let source_path_segments = prepare_path_segments(env, jni_source_path_segments);
-            let target_path_segments = prepare_path_segments(env, jni_target_path_segments);
-            let result = helper.synced_mv(&source_path_segments, &target_path_segments);
-            trace!("**********************mvNative finished**************");
-            if result.is_ok() {
-                let cid = result.ok().unwrap();
-                unsafe{
-                    return serialize_config_result(env, None, Some(cid));
-                }
-            }else {
-                let msg = result.err().unwrap();
-                trace!("wnfsError occured in Java_land_fx_wnfslib_Fs_mvNative: {:?}", msg.to_owned());
-                unsafe{
-                    return serialize_config_result(env, Some(msg.to_owned()), None);
-                }
+        trace!("**********************syncPendingNative started**************");
+        let result = catch_panic(|| {
+            let Some(queue) = upload_queue() else {
+                return Ok(0);
+            };
+            let store = DiskCachedStore::new(JNIStore::new(env, jni_fula_client), disk_cache());
+            let sent = queue.drain(&store)?;
+            Ok(sent as jlong)
+        });
+        trace!("**********************syncPendingNative finished**************");
+        match result {
+            Ok(sent) => unsafe { serialize_long_result(env, None, Some(sent)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_syncPendingNative: {:?}", msg);
+                unsafe { serialize_long_result(env, Some(msg), None) }
             }
+        }
+    }
 
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_mvNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_config_result(env, Some(msg.to_owned()), None);
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_closeSessionNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_handle: jlong,
+    ) -> jobject {
+        trace!("**********************closeSessionNative started**************");
+        match catch_panic(|| close_session(jni_handle)) {
+            Ok(()) => unsafe { serialize_result(env, None) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_closeSessionNative: {:?}", msg);
+                unsafe { serialize_result(env, Some(msg)) }
             }
         }
-
-
-
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_cpNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_flushSessionNative(
         env: JNIEnv,
         _: JClass,
-        jni_fula_client: JObject,
-        jni_cid: JString,
-
-        jni_source_path_segments: JString,
-        jni_target_path_segments: JString,
+        jni_handle: jlong,
     ) -> jobject {
-        trace!("**********************cpNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-            let source_path_segments = prepare_path_segments(env, jni_source_path_segments);
-            let target_path_segments = prepare_path_segments(env, jni_target_path_segments);
-            let result = helper.synced_cp(&source_path_segments, &target_path_segments);
-            trace!("**********************cpNative finished**************");
-            if result.is_ok() {
-                let cid = result.ok().unwrap();
-                unsafe{
-                    return serialize_config_result(env, None, Some(cid));
-                }
-            }else {
-                let msg = result.err().unwrap();
-                trace!("wnfsError occured in Java_land_fx_wnfslib_Fs_cpNative: {:?}", msg.to_owned());
-                unsafe{
-                    return serialize_config_result(env, Some(msg.to_owned()), None);
-                }
+        trace!("**********************flushSessionNative started**************");
+        let result = catch_panic(|| with_session(jni_handle, |drive| drive.flush()));
+        trace!("**********************flushSessionNative finished**************");
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_flushSessionNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
             }
+        }
+    }
 
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_cpNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_config_result(env, Some(msg.to_owned()), None);
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_mkdirSessionNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_handle: jlong,
+        jni_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************mkdirSessionNative started**************");
+        let result = catch_panic(|| {
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            with_session(jni_handle, |drive| drive.mkdir(&path_segments))
+        });
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_mkdirSessionNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
             }
         }
+    }
 
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_writeFileSessionNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_handle: jlong,
+        jni_path_segments: JString,
+        jni_content: jbyteArray,
+    ) -> jobject {
+        trace!("**********************writeFileSessionNative started**************");
+        let result = catch_panic(|| {
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let content = jbyte_array_to_vec(env, jni_content)?;
+            with_session(jni_handle, |drive| drive.write(&path_segments, content))
+        });
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_writeFileSessionNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_rmNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_readFileSessionNative(
         env: JNIEnv,
         _: JClass,
-        jni_fula_client: JObject,
-        jni_cid: JString,
+        jni_handle: jlong,
+        jni_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************readFileSessionNative started**************");
+        let result = catch_panic(|| {
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            with_session(jni_handle, |drive| drive.read(&path_segments))
+        });
+        match result {
+            Ok(content) => unsafe { serialize_bytes_result(env, None, Some(content)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_readFileSessionNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
+            }
+        }
+    }
 
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_lsSessionNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_handle: jlong,
         jni_path_segments: JString,
     ) -> jobject {
-        trace!("**********************rmNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-            let path_segments = prepare_path_segments(env, jni_path_segments);
-            let rm_res = helper.synced_rm(&path_segments);
-            if rm_res.is_ok() {
-                let cid = rm_res.ok().unwrap();
-                trace!("**********************rmNative finished**************");
-                unsafe{return serialize_config_result(env, None, Some(cid))}
-            } else {
-                let msg = rm_res
-                    .err()
-                    .unwrap();
-                trace!("wnfsError in Java_land_fx_wnfslib_Fs_rmNative: {:?}", msg.to_owned());
-                unsafe{return serialize_config_result(env, Some(msg.to_owned()), None);}
+        trace!("**********************lsSessionNative started**************");
+        let result = catch_panic(|| {
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            let entries = with_session(jni_handle, |drive| drive.ls(&path_segments))?;
+            prepare_ls_output(entries)
+        });
+        match result {
+            Ok(output) => unsafe { serialize_bytes_result(env, None, Some(output)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_lsSessionNative: {:?}", msg);
+                unsafe { serialize_bytes_result(env, Some(msg), None) }
             }
-        } else{
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError in Java_land_fx_wnfslib_Fs_rmNative: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_config_result(env, Some(msg.to_owned()), None);
+        }
+    }
+
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_rmSessionNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_handle: jlong,
+        jni_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************rmSessionNative started**************");
+        let result = catch_panic(|| {
+            let path_segments = prepare_path_segments(env, jni_path_segments)?;
+            with_session(jni_handle, |drive| drive.rm(&path_segments))
+        });
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_rmSessionNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
             }
         }
     }
 
     #[no_mangle]
-    pub extern "C" fn Java_land_fx_wnfslib_Fs_lsNative(
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_mvSessionNative(
         env: JNIEnv,
         _: JClass,
-        jni_fula_client: JObject,
-        jni_cid: JString,
+        jni_handle: jlong,
+        jni_source_path_segments: JString,
+        jni_target_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************mvSessionNative started**************");
+        let result = catch_panic(|| {
+            let source_path_segments = prepare_path_segments(env, jni_source_path_segments)?;
+            let target_path_segments = prepare_path_segments(env, jni_target_path_segments)?;
+            with_session(jni_handle, |drive| drive.mv(&source_path_segments, &target_path_segments))
+        });
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_mvSessionNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
+            }
+        }
+    }
 
-        jni_path_segments: JString,
-    ) -> jbyteArray {
-        trace!("**********************lsNative started**************");
-        let store = JNIStore::new(env, jni_fula_client);
-        let block_store = &mut FFIFriendlyBlockStore::new(Box::new(store));
-        let cid = deserialize_cid(env, jni_cid);
-        let helper_res =PrivateDirectoryHelper::synced_reload(block_store, cid);
-
-        if helper_res.is_ok() {
-            let helper = &mut helper_res.ok().unwrap();
-                let path_segments = prepare_path_segments(env, jni_path_segments);
-                let ls_res = helper.synced_ls_files(&path_segments);
-                if ls_res.is_ok() {
-                    let output =
-                        prepare_ls_output(ls_res.ok().unwrap());
-                    trace!("**********************lsNative finished**************");
-                    if output.is_ok() {
-                        let res = output.ok().unwrap();
-                        unsafe { return serialize_bytes_result(env, None, Some(res));}
-                    } else {
-                        let msg = output.err().unwrap().to_string();
-                        trace!("wnfsError occured in Java_land_fx_wnfslib_Fs_lsNative output: {:?}", msg.to_owned());
-                        unsafe { return serialize_bytes_result(env, Some(msg), None);}
-                    }
-                } else {
-                    let msg = ls_res.err().unwrap();
-                    trace!("wnfsError occured in Java_land_fx_wnfslib_Fs_lsNative ls_res: {:?}", msg.to_owned());
-                    unsafe{
-                        return serialize_bytes_result(env, Some(msg.to_owned()), None);
-                    }
-                }
-        } else {
-            let msg = helper_res.err().unwrap();
-            trace!("wnfsError occured in Java_land_fx_wnfslib_Fs_lsNative forest_res: {:?}", msg.to_owned());
-            unsafe{
-                return serialize_bytes_result(env, Some(msg.to_owned()), None);
+    #[no_mangle]
+    pub extern "C" fn Java_land_fx_wnfslib_Fs_cpSessionNative(
+        env: JNIEnv,
+        _: JClass,
+        jni_handle: jlong,
+        jni_source_path_segments: JString,
+        jni_target_path_segments: JString,
+    ) -> jobject {
+        trace!("**********************cpSessionNative started**************");
+        let result = catch_panic(|| {
+            let source_path_segments = prepare_path_segments(env, jni_source_path_segments)?;
+            let target_path_segments = prepare_path_segments(env, jni_target_path_segments)?;
+            with_session(jni_handle, |drive| drive.cp(&source_path_segments, &target_path_segments))
+        });
+        match result {
+            Ok(cid) => unsafe { serialize_config_result(env, None, Some(cid)) },
+            Err(msg) => {
+                trace!("wnfsError in Java_land_fx_wnfslib_Fs_cpSessionNative: {:?}", msg);
+                unsafe { serialize_config_result(env, Some(msg), None) }
             }
         }
     }
@@ -697,9 +1763,10 @@ pub mod android {
 
     pub unsafe fn serialize_bytes_result(env: JNIEnv, err: Option<String>, bytes: Option<Vec<u8>>) -> jobject {
         trace!("**********************serialize_result started**************");
-        let result = match bytes {
-                Some(bytes) => vec_to_jbyte_array(env, bytes),
-                None => JObject::null().into_inner(),
+        let (err, result) = match bytes.map(|bytes| vec_to_jbyte_array(env, bytes)) {
+            Some(Ok(bytes)) => (err, bytes),
+            Some(Err(msg)) => (Some(msg), JObject::null().into_inner()),
+            None => (err, JObject::null().into_inner()),
         };
         create_result_object(env, "BytesResult".into(), "[B".into(),err, result.into())
     }
@@ -713,12 +1780,30 @@ pub mod android {
         create_result_object(env, "StringResult".into(), "Ljava/lang/String;".into(),err, result.into())
     }
 
+    pub unsafe fn serialize_long_result(env: JNIEnv, err: Option<String>, value: Option<jlong>) -> jobject {
+        trace!("**********************serialize_long_result started**************");
+        let result: JObject = match value {
+            Some(value) => env
+                .new_object("java/lang/Long", "(J)V", &[JValue::Long(value)])
+                .unwrap_or(JObject::null()),
+            None => JObject::null(),
+        };
+        create_result_object(env, "LongResult".into(), "Ljava/lang/Long;".into(), err, result)
+    }
+
     pub fn serialize_config(env: JNIEnv, cid: Cid) -> jobject {
         // Get the Config class
-        let config_class = env.find_class("land/fx/wnfslib/Config").unwrap();
+        let config_class = match env.find_class("land/fx/wnfslib/Config") {
+            Ok(config_class) => config_class,
+            Err(err) => {
+                trace!("wnfsError occured in serialize_config find_class: {:?}", err.to_string());
+                take_java_exception(env);
+                return JObject::null().into_inner();
+            }
+        };
 
         // Convert the Cid to a string
-        let cid_string = env.new_string(cid.to_string()).unwrap();
+        let cid_string = serialize_cid(env, cid);
 
         // Create a new Config object
         let create_config_object_fn_res = env
@@ -735,11 +1820,19 @@ pub mod android {
                     create_config_object_fn,
                     JavaType::Object(format!("land/fx/wnfslib/result/Config")),
                     &[JValue::Object(cid_string.into())],
-                ).expect("Couldn't create new Config object");
-
-                return result_res.l().unwrap().into_inner();
+                )
+                .and_then(|result| result.l());
+            match result_res {
+                Ok(result) => result.into_inner(),
+                Err(err) => {
+                    trace!("wnfsError occured in serialize_config result_res: {:?}", err.to_string());
+                    take_java_exception(env);
+                    JObject::null().into_inner()
+                }
+            }
         } else {
             trace!("wnfsError occured in serialize_config create_config_object_fn_res: {:?}", create_config_object_fn_res.err().unwrap().to_string());
+            take_java_exception(env);
             return JObject::null().into_inner();
         }
     }
@@ -759,24 +1852,35 @@ pub mod android {
     }
 
     pub fn create_result_object(env: JNIEnv, java_class_name: String, java_object_path: String, err: Option<String>, result: JObject) -> jobject {
-        ////let result_cls = env.find_class("land/fx/wnfslib/result/ConfigResult").unwrap();
-        let result_cls = env.find_class(format!("land/fx/wnfslib/result/{}", java_class_name))
-            .expect(format!("class result {} not found", java_class_name).as_str());
-            //.unwrap();
+        // Calling into the JVM with an exception pending is not allowed, and the
+        // caller gets a proper error result instead of the stray exception.
+        if let Some(exception) = take_java_exception(env) {
+            trace!("wnfsError cleared pending Java exception in create_result_object: {}", exception);
+        }
+        let result_cls = match env.find_class(format!("land/fx/wnfslib/result/{}", java_class_name)) {
+            Ok(result_cls) => result_cls,
+            Err(err) => {
+                trace!("wnfsError class result {} not found: {:?}", java_class_name, err.to_string());
+                return JObject::null().into_inner();
+            }
+        };
         trace!("**********************create_result_object result_cls set**************");
         let create_result_fn_res = env
             .get_static_method_id(
                 result_cls,
                 "create",
-                format!("(Ljava/lang/String;{})Lland/fx/wnfslib/result/{};", java_object_path, java_class_name),
+                format!("(ILjava/lang/String;{})Lland/fx/wnfslib/result/{};", java_object_path, java_class_name),
             );
         if create_result_fn_res.is_ok() {
             let create_result_fn = create_result_fn_res.ok().unwrap();
 
             trace!("**********************create_result_object create_result_fn set**************");
-            let err_java = match err {
-                Some(err) => serialize_string(env, err),
-                None => JObject::null().into(),
+            let (code, err_java) = match err {
+                Some(err) => (error_code(&err), serialize_string(env, err)),
+                None => {
+                    take_store_error();
+                    (ErrorCode::Unknown, JObject::null().into())
+                }
             };
             trace!("**********************create_result_object almost finished**************");
             trace!("Result object: {:?}", result);
@@ -785,7 +1889,7 @@ pub mod android {
                     result_cls,
                     create_result_fn,
                     JavaType::Object(format!("land/fx/wnfslib/result/{}", java_class_name)),
-                    &[JValue::from(err_java), JValue::from(result)],
+                    &[JValue::Int(code as i32), JValue::from(err_java), JValue::from(result)],
                 );
             if result_res.is_ok() {
                 let result_l = result_res
@@ -811,15 +1915,24 @@ pub mod android {
         }
     }
 
-    pub fn deserialize_cid(env: JNIEnv, jni_cid: JString) -> Cid {
-        let cid: String = env.get_string(jni_cid).expect("Failed to parse cid").into();
-        let cid = Cid::try_from(cid).unwrap();
+    pub fn deserialize_cid(env: JNIEnv, jni_cid: JString) -> Result<Cid, String> {
+        let cid: String = env
+            .get_string(jni_cid)
+            .map_err(|err| format!("invalid cid: {}", err))?
+            .into();
+        let cid = Cid::try_from(cid.clone()).map_err(|err| format!("invalid cid {:?}: {}", cid, err))?;
         trace!("**********************deserialize_cid started**************");
         trace!(
             "**********************deserialize_cid cid={}",
             cid.to_string()
         );
-        cid
+        Ok(cid)
+    }
+
+    pub fn deserialize_filename(env: JNIEnv, jni_filename: JString) -> Result<String, String> {
+        env.get_string(jni_filename)
+            .map(String::from)
+            .map_err(|err| format!("invalid filename: {}", err))
     }
 
 
@@ -829,11 +1942,10 @@ pub mod android {
             "**********************serialize_string text={:?}",
             text
         );
-        let a: JString = env
-            .new_string(text)
-            .expect("Failed to serialize text")
-            .into();
-        a
+        env.new_string(text).unwrap_or_else(|err| {
+            trace!("wnfsError Failed to serialize text: {:?}", err.to_string());
+            JObject::null().into()
+        })
     }
 
     pub fn serialize_cid(env: JNIEnv, cid: Cid) -> JString {
@@ -842,67 +1954,101 @@ pub mod android {
             "**********************serialize_cid cid={:?}",
             cid.to_string()
         );
-        let a: JString = env
-            .new_string(cid.to_string())
-            .expect("Failed to serialize cid")
-            .into();
-        a
+        serialize_string(env, cid.to_string())
     }
 
 
 
-    pub fn prepare_path_segments(env: JNIEnv, jni_path_segments: JString) -> Vec<String> {
+    pub fn prepare_path_segments(env: JNIEnv, jni_path_segments: JString) -> Result<Vec<String>, String> {
         let path: String = env
             .get_string(jni_path_segments)
-            .expect("Failed to parse input path segments")
+            .map_err(|err| format!("invalid path: {}", err))?
             .into();
 
-        PrivateDirectoryHelper::parse_path(path)
-            .iter()
-            .map(|s| s.to_string())
-            .collect()
-    }
-
-    pub fn prepare_ls_output(ls_result: Vec<(String, Metadata)>) -> Result<Vec<u8>, String> {
-
-        let mut result: Vec<u8> = Vec::new();
-
-        let item_separator = "???".to_owned();
-        let line_separator = "!!!".to_owned();
-                    for item in ls_result.iter() {
-
-                        let created = item.1.clone().get_created();
-                        let modification = item.1.clone().get_modified();
-                        if created.is_some() && modification.is_some() {
-                            let filename: String = item.0.clone().to_string().to_owned();
-                            let creation_time: String = created.unwrap().to_string().to_owned();
-                            let modification_time: String = modification.unwrap().to_string().to_owned();
-
-                            let row_string: String = format!("{}{}{}{}{}{}",
-                                filename
-                                , item_separator
-                                , creation_time
-                                , item_separator
-                                , modification_time
-                                , line_separator
-                            );
-                            let row_byte = row_string.as_bytes().to_vec();
-                            result.append(&mut row_byte.to_owned());
-                        }
+        Ok(WnfsDrive::parse_path(&path))
+    }
+
+    /// Decodes the parallel arrays built by `Transaction`: operation `i` is
+    /// `kinds[i]` applied to `paths[i]`, with `targets[i]` for mv/cp and
+    /// `contents[i]` for writeFile. Unused slots are null.
+    pub fn prepare_transaction_ops(
+        env: JNIEnv,
+        jni_kinds: jobjectArray,
+        jni_paths: jobjectArray,
+        jni_targets: jobjectArray,
+        jni_contents: jobjectArray,
+    ) -> Result<Vec<Op>, String> {
+        let element = |array: jobjectArray, index: i32| {
+            env.get_object_array_element(array, index)
+                .map_err(|err| format!("invalid transaction: {}", err))
+        };
+        let count = env
+            .get_array_length(jni_kinds)
+            .map_err(|err| format!("invalid transaction: {}", err))?;
+
+        let mut ops = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            let kind = deserialize_filename(env, JString::from(element(jni_kinds, index)?))?;
+            let path = prepare_path_segments(env, JString::from(element(jni_paths, index)?))?;
+            let op = match kind.as_str() {
+                "mkdir" => Op::Mkdir { path },
+                "rm" => Op::Rm { path },
+                "writeFile" => {
+                    let content = jbyte_array_to_vec(env, element(jni_contents, index)?.into_inner())?;
+                    Op::WriteFile { path, content }
+                }
+                "mv" | "cp" => {
+                    let target = prepare_path_segments(env, JString::from(element(jni_targets, index)?))?;
+                    if kind == "mv" {
+                        Op::Mv { source: path, target }
+                    } else {
+                        Op::Cp { source: path, target }
                     }
-                    Ok(result)
+                }
+                other => return Err(format!("invalid transaction: unknown operation {}", other)),
+            };
+            ops.push(op);
+        }
+        Ok(ops)
+    }
+
+    pub fn deserialize_offset(jni_offset: jlong) -> Result<u64, String> {
+        u64::try_from(jni_offset).map_err(|_| format!("invalid range: offset {} is negative", jni_offset))
+    }
+
+    pub fn deserialize_cache_bytes(jni_cache_bytes: jlong) -> Result<usize, String> {
+        usize::try_from(jni_cache_bytes).map_err(|_| format!("invalid cache size: {} is negative", jni_cache_bytes))
+    }
+
+    pub fn deserialize_put_policy(jni_policy: jint) -> Result<PutPolicy, String> {
+        PutPolicy::from_i32(jni_policy).ok_or_else(|| format!("invalid put policy: {}", jni_policy))
+    }
+
+    /// Encodes directory entries as a UTF-8 JSON array of
+    /// `{name, kind, size, created, modified, cid}` objects.
+    pub fn prepare_ls_output(entries: Vec<Entry>) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(&entries).map_err(|err| format!("encoding ls output: {}", err))
+    }
+
+    /// Encodes a single node as a UTF-8 JSON object with the same fields as an
+    /// `ls` entry (minus `name`) plus any user `metadata`.
+    pub fn prepare_stat_output(info: NodeInfo) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(&info).map_err(|err| format!("encoding stat output: {}", err))
+    }
 
+    /// Encodes a directory transfer report as a UTF-8 JSON object with `cid`,
+    /// `succeeded`, `skipped` and `failed` (`{path, error, code}`) fields.
+    pub fn prepare_transfer_output(report: TransferReport) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(&report).map_err(|err| format!("encoding transfer report: {}", err))
     }
 
-    pub fn jbyte_array_to_vec(env: JNIEnv, jni_content: jbyteArray) -> Vec<u8> {
+    pub fn jbyte_array_to_vec(env: JNIEnv, jni_content: jbyteArray) -> Result<Vec<u8>, String> {
         env.convert_byte_array(jni_content)
-            .expect("converting jbyteArray to Vec<u8>")
-            .into()
+            .map_err(|err| format!("invalid byte array: {}", err))
     }
 
-    pub fn vec_to_jbyte_array(env: JNIEnv, jni_content: Vec<u8>) -> jbyteArray {
+    pub fn vec_to_jbyte_array(env: JNIEnv, jni_content: Vec<u8>) -> Result<jbyteArray, String> {
         env.byte_array_from_slice(jni_content.as_slice())
-            .expect("converting Vec<u8> to jbyteArray")
-            .into()
+            .map_err(|err| format!("converting Vec<u8> to jbyteArray: {}", err))
     }
 }
@@ -0,0 +1,277 @@
+//! Node-level queries on a loaded forest that `PrivateDirectoryHelper`'s
+//! `synced_*` functions do not expose: entry kinds, sizes and content CIDs.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use futures::executor::block_on;
+use futures::io::AllowStdIo;
+use libipld::{Cid, Ipld};
+use serde::Serialize;
+use wnfs::common::Metadata;
+use wnfs::private::PrivateNode;
+use wnfsutils::private_forest::PrivateDirectoryHelper;
+
+use crate::progress::{Cancel, Progress, ProgressReader, CANCELLED};
+
+/// Bytes requested per `read_at` call when streaming a range to disk.
+const STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    File,
+    Directory,
+}
+
+/// Everything we report about a single file or directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeInfo {
+    pub kind: NodeKind,
+    /// Content length in bytes for files, number of children for directories.
+    pub size: u64,
+    /// Seconds since the Unix epoch, if recorded in the node's metadata.
+    pub created: Option<i64>,
+    pub modified: Option<i64>,
+    /// CID of the encrypted block holding this revision of the node, or `None`
+    /// if it has changed since it was last stored.
+    pub cid: Option<String>,
+    /// Metadata entries other than the created/modified timestamps.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, Ipld>,
+}
+
+/// A child of a directory, as returned by `ls`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    pub name: String,
+    #[serde(flatten)]
+    pub info: NodeInfo,
+}
+
+/// Resolves `path_segments` to a node. An empty path is the root directory.
+pub fn get_node(helper: &PrivateDirectoryHelper, path_segments: &[String]) -> Result<Option<PrivateNode>, String> {
+    if path_segments.is_empty() {
+        return Ok(Some(PrivateNode::Dir(helper.root_dir.clone())));
+    }
+    block_on(helper.root_dir.get_node(path_segments, true, &helper.forest, &helper.store))
+        .map_err(|err| err.to_string())
+}
+
+/// Resolves `path_segments` to a file node, failing for directories.
+pub fn get_file(helper: &PrivateDirectoryHelper, path_segments: &[String]) -> Result<PrivateNode, String> {
+    match get_node(helper, path_segments)? {
+        Some(node @ PrivateNode::File(_)) => Ok(node),
+        Some(PrivateNode::Dir(_)) => Err(format!("Expected a file: {}", path_segments.join("/"))),
+        None => Err(format!("Cannot find file: {}", path_segments.join("/"))),
+    }
+}
+
+pub fn stat(helper: &PrivateDirectoryHelper, path_segments: &[String]) -> Result<NodeInfo, String> {
+    match get_node(helper, path_segments)? {
+        Some(node) => node_info(helper, &node),
+        None => Err(format!("Cannot find file or directory: {}", path_segments.join("/"))),
+    }
+}
+
+/// Reads up to `length` bytes starting at `offset`, or everything after `offset`
+/// if `length` is `None`. Only the content blocks covering the range are fetched.
+pub fn read_range(
+    helper: &PrivateDirectoryHelper,
+    path_segments: &[String],
+    offset: u64,
+    length: Option<usize>,
+) -> Result<Vec<u8>, String> {
+    let node = get_file(helper, path_segments)?;
+    let file = node.as_file().map_err(|err| err.to_string())?;
+    block_on(file.read_at(offset, length, &helper.forest, &helper.store)).map_err(|err| err.to_string())
+}
+
+/// Writes the file content from `offset` onwards into the local file `filename`
+/// at the same position, truncating anything after `offset` first. With an
+/// offset of 0 this is a full download; otherwise it resumes a partial one, so
+/// the local file must already hold at least `offset` bytes and `offset` must
+/// not be past the end of the file.
+pub fn read_to_path(
+    helper: &PrivateDirectoryHelper,
+    path_segments: &[String],
+    filename: &str,
+    offset: u64,
+    progress: &mut Progress,
+    cancel: &Cancel,
+) -> Result<(), String> {
+    let node = get_file(helper, path_segments)?;
+    let file = node.as_file().map_err(|err| err.to_string())?;
+    let size = block_on(file.size(&helper.forest, &helper.store)).map_err(|err| err.to_string())?;
+    if offset > size {
+        return Err(format!("invalid range: offset {} is past the end of the file ({} bytes)", offset, size));
+    }
+
+    let mut out = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .open(filename)
+        .map_err(|err| format!("opening {}: {}", filename, err))?;
+    let local = out.metadata().map_err(|err| format!("reading {}: {}", filename, err))?.len();
+    if local < offset {
+        return Err(format!(
+            "invalid range: cannot resume at offset {}, {} only holds {} bytes",
+            offset, filename, local
+        ));
+    }
+    out.set_len(offset).map_err(|err| format!("truncating {}: {}", filename, err))?;
+    out.seek(SeekFrom::Start(offset)).map_err(|err| format!("seeking {}: {}", filename, err))?;
+
+    let mut position = offset;
+    while position < size {
+        // What was written so far stays on disk, so a cancelled download can be resumed.
+        cancel.check()?;
+        let chunk = block_on(file.read_at(position, Some(STREAM_CHUNK_SIZE), &helper.forest, &helper.store))
+            .map_err(|err| err.to_string())?;
+        if chunk.is_empty() {
+            break;
+        }
+        out.write_all(&chunk).map_err(|err| format!("writing {}: {}", filename, err))?;
+        position += chunk.len() as u64;
+        progress.report(position, size);
+    }
+    out.flush().map_err(|err| format!("writing {}: {}", filename, err))
+}
+
+/// Streams the local file `local` into `path_segments`, creating or replacing
+/// it with `time` as its modification time. Nothing is committed; callers
+/// follow up with [`commit`] once all their changes are in.
+pub fn stream_from_path(
+    helper: &mut PrivateDirectoryHelper,
+    path_segments: &[String],
+    local: &Path,
+    time: DateTime<Utc>,
+    progress: &mut Progress,
+    cancel: &Cancel,
+) -> Result<(), String> {
+    let content = std::fs::File::open(local).map_err(|err| format!("opening {}: {}", local.display(), err))?;
+    let total = content
+        .metadata()
+        .map_err(|err| format!("reading {}: {}", local.display(), err))?
+        .len();
+    let mut rng = rand::thread_rng();
+    let file = block_on(helper.root_dir.open_file_mut(path_segments, true, time, &mut helper.forest, &helper.store, &mut rng))
+        .map_err(|err| err.to_string())?;
+    let reader = ProgressReader::new(AllowStdIo::new(content), total, progress, cancel);
+    block_on(file.set_content(time, reader, &mut helper.forest, &helper.store, &mut rng)).map_err(|err| {
+        if cancel.is_cancelled() {
+            CANCELLED.to_string()
+        } else {
+            err.to_string()
+        }
+    })
+}
+
+/// Resolves `path_segments` without reading file content. A missing path is
+/// `Ok(None)`; store and decryption failures are still errors.
+pub fn exists(helper: &PrivateDirectoryHelper, path_segments: &[String]) -> Result<Option<NodeKind>, String> {
+    Ok(get_node(helper, path_segments)?.map(|node| match node {
+        PrivateNode::File(_) => NodeKind::File,
+        PrivateNode::Dir(_) => NodeKind::Directory,
+    }))
+}
+
+/// Reads every directory and file under `path_segments`, content included,
+/// so that each block needed to read the subtree is fetched from the store.
+pub fn read_tree(helper: &PrivateDirectoryHelper, path_segments: &[String]) -> Result<(), String> {
+    match get_node(helper, path_segments)? {
+        Some(PrivateNode::Dir(dir)) => {
+            for name in dir.get_entries() {
+                let mut child_path = path_segments.to_vec();
+                child_path.push(name.clone());
+                read_tree(helper, &child_path)?;
+            }
+            Ok(())
+        }
+        Some(PrivateNode::File(file)) => {
+            let size = block_on(file.size(&helper.forest, &helper.store)).map_err(|err| err.to_string())?;
+            let mut position = 0;
+            while position < size {
+                let chunk = block_on(file.read_at(position, Some(STREAM_CHUNK_SIZE), &helper.forest, &helper.store))
+                    .map_err(|err| err.to_string())?;
+                if chunk.is_empty() {
+                    break;
+                }
+                position += chunk.len() as u64;
+            }
+            Ok(())
+        }
+        None => Err(format!("Cannot find file or directory: {}", path_segments.join("/"))),
+    }
+}
+
+/// Lists every child of the directory at `path_segments`, including children
+/// whose metadata carries no timestamps.
+pub fn ls(helper: &PrivateDirectoryHelper, path_segments: &[String]) -> Result<Vec<Entry>, String> {
+    let dir = match get_node(helper, path_segments)? {
+        Some(PrivateNode::Dir(dir)) => dir,
+        Some(PrivateNode::File(_)) => {
+            return Err(format!("Expected a directory: {}", path_segments.join("/")))
+        }
+        None => return Err(format!("Cannot find directory: {}", path_segments.join("/"))),
+    };
+
+    let mut entries = Vec::new();
+    for name in dir.get_entries() {
+        let mut child_path = path_segments.to_vec();
+        child_path.push(name.clone());
+        let node = get_node(helper, &child_path)?
+            .ok_or_else(|| format!("Cannot find file or directory: {}", child_path.join("/")))?;
+        entries.push(Entry {
+            name: name.clone(),
+            info: node_info(helper, &node)?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Stores the root directory and the forest, returning the forest CID that
+/// `synced_load_with_wnfs_key` accepts. The `synced_*` mutators do this after
+/// every call; functions here that batch several changes call it once at the end.
+pub fn commit(helper: &mut PrivateDirectoryHelper) -> Result<Cid, String> {
+    block_on(helper.root_dir.as_node().store(&mut helper.forest, &helper.store, &mut rand::thread_rng()))
+        .map_err(|err| err.to_string())?;
+    block_on(helper.store.put_async_serializable(helper.forest.as_ref())).map_err(|err| err.to_string())
+}
+
+pub fn node_info(helper: &PrivateDirectoryHelper, node: &PrivateNode) -> Result<NodeInfo, String> {
+    let (kind, size, metadata) = match node {
+        PrivateNode::File(file) => {
+            let size = block_on(file.size(&helper.forest, &helper.store)).map_err(|err| err.to_string())?;
+            (NodeKind::File, size, file.get_metadata())
+        }
+        PrivateNode::Dir(dir) => (NodeKind::Directory, dir.get_entries().count() as u64, dir.get_metadata()),
+    };
+    Ok(NodeInfo {
+        kind,
+        size,
+        created: metadata.get_created().map(|time| time.timestamp()),
+        modified: metadata.get_modified().map(|time| time.timestamp()),
+        cid: content_cid(node).map(|cid| cid.to_string()),
+        metadata: user_metadata(metadata),
+    })
+}
+
+/// The CID the node was stored or loaded under. A node changed since the last
+/// store has none yet; storing it here would encrypt it with a fresh key and
+/// hand back a CID that the next commit does not use.
+fn content_cid(node: &PrivateNode) -> Option<Cid> {
+    node.get_persisted_as().get().copied()
+}
+
+fn user_metadata(metadata: &Metadata) -> BTreeMap<String, Ipld> {
+    metadata
+        .0
+        .iter()
+        .filter(|(key, _)| key.as_str() != "created" && key.as_str() != "modified")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
@@ -0,0 +1,110 @@
+//! Throttled progress reporting and cancellation for streaming reads and writes.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::io::AsyncRead;
+
+pub struct Progress<'a> {
+    callback: Option<Box<dyn FnMut(u64, u64) + 'a>>,
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl<'a> Progress<'a> {
+    /// Calls `callback(processed, total)` at most once per `interval`, plus
+    /// always on completion.
+    pub fn new(callback: impl FnMut(u64, u64) + 'a, interval: Duration) -> Self {
+        Self { callback: Some(Box::new(callback)), interval, last: None }
+    }
+
+    pub fn none() -> Self {
+        Self { callback: None, interval: Duration::ZERO, last: None }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.callback.is_none()
+    }
+
+    pub fn report(&mut self, processed: u64, total: u64) {
+        let Some(callback) = self.callback.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        let due = self.last.map_or(true, |last| now.duration_since(last) >= self.interval);
+        if due || processed >= total {
+            self.last = Some(now);
+            callback(processed, total);
+        }
+    }
+}
+
+/// Error message for an aborted transfer; `ErrorCode::from_message` maps it
+/// to `Cancelled`.
+pub const CANCELLED: &str = "operation cancelled";
+
+/// Polled between chunks of a transfer to find out whether to stop.
+pub struct Cancel<'a> {
+    cancelled: Option<Box<dyn Fn() -> bool + 'a>>,
+}
+
+impl<'a> Cancel<'a> {
+    pub fn new(cancelled: impl Fn() -> bool + 'a) -> Self {
+        Self { cancelled: Some(Box::new(cancelled)) }
+    }
+
+    pub fn none() -> Self {
+        Self { cancelled: None }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.cancelled.is_none()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.as_ref().map_or(false, |cancelled| cancelled())
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reports how much of `inner` has been consumed as it is read, and fails the
+/// read once `cancel` trips.
+pub struct ProgressReader<'p, 'a, R> {
+    inner: R,
+    progress: &'p mut Progress<'a>,
+    cancel: &'p Cancel<'a>,
+    processed: u64,
+    total: u64,
+}
+
+impl<'p, 'a, R> ProgressReader<'p, 'a, R> {
+    pub fn new(inner: R, total: u64, progress: &'p mut Progress<'a>, cancel: &'p Cancel<'a>) -> Self {
+        Self { inner, progress, cancel, processed: 0, total }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<'_, '_, R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        // Not `Interrupted`: readers retry on that kind, and would go on
+        // polling a cancelled transfer.
+        if this.cancel.is_cancelled() {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, CANCELLED)));
+        }
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(read @ 1..)) = poll {
+            this.processed += read as u64;
+            this.progress.report(this.processed, this.total);
+        }
+        poll
+    }
+}
@@ -0,0 +1,75 @@
+//! Applies a batch of mutations to one loaded forest and stores only the
+//! final root. If any step fails nothing is committed, so the root the batch
+//! started from stays the current one.
+
+use chrono::Utc;
+use futures::executor::block_on;
+use libipld::Cid;
+use wnfsutils::private_forest::PrivateDirectoryHelper;
+
+use crate::nodes;
+
+pub enum Op {
+    Mkdir { path: Vec<String> },
+    WriteFile { path: Vec<String>, content: Vec<u8> },
+    Rm { path: Vec<String> },
+    Mv { source: Vec<String>, target: Vec<String> },
+    Cp { source: Vec<String>, target: Vec<String> },
+}
+
+impl Op {
+    fn describe(&self) -> String {
+        match self {
+            Op::Mkdir { path } => format!("mkdir {}", path.join("/")),
+            Op::WriteFile { path, .. } => format!("writeFile {}", path.join("/")),
+            Op::Rm { path } => format!("rm {}", path.join("/")),
+            Op::Mv { source, target } => format!("mv {} {}", source.join("/"), target.join("/")),
+            Op::Cp { source, target } => format!("cp {} {}", source.join("/"), target.join("/")),
+        }
+    }
+
+    fn apply(self, helper: &mut PrivateDirectoryHelper) -> Result<(), String> {
+        let forest = &mut helper.forest;
+        let store = &helper.store;
+        let rng = &mut rand::thread_rng();
+        let result = match self {
+            Op::Mkdir { path } => block_on(helper.root_dir.mkdir(&path, true, Utc::now(), forest, store, rng)),
+            Op::WriteFile { path, content } => {
+                block_on(helper.root_dir.write(&path, true, Utc::now(), content, forest, store, rng))
+            }
+            Op::Rm { path } => block_on(helper.root_dir.rm(&path, true, forest, store)).map(|_| ()),
+            Op::Mv { source, target } => {
+                block_on(helper.root_dir.basic_mv(&source, &target, true, Utc::now(), forest, store, rng))
+            }
+            Op::Cp { source, target } => {
+                block_on(helper.root_dir.cp(&source, &target, true, Utc::now(), forest, store, rng))
+            }
+        };
+        result.map_err(|err| err.to_string())
+    }
+}
+
+/// Applies `ops` in order and commits once. The error names the failing step.
+/// On failure the root directory and forest are put back as they were, so a
+/// later commit on the same helper does not pick up the earlier steps.
+pub fn apply(helper: &mut PrivateDirectoryHelper, ops: Vec<Op>) -> Result<Cid, String> {
+    // The ops copy the root and forest on write, so holding on to the current
+    // `Rc`s keeps the state to roll back to.
+    let root_dir = helper.root_dir.clone();
+    let forest = helper.forest.clone();
+    let result = apply_all(helper, ops).and_then(|()| nodes::commit(helper));
+    if result.is_err() {
+        helper.root_dir = root_dir;
+        helper.forest = forest;
+    }
+    result
+}
+
+fn apply_all(helper: &mut PrivateDirectoryHelper, ops: Vec<Op>) -> Result<(), String> {
+    for (index, op) in ops.into_iter().enumerate() {
+        let step = op.describe();
+        op.apply(helper)
+            .map_err(|err| format!("transaction step {} ({}) failed, nothing was committed: {}", index, step, err))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,262 @@
+//! Whole-directory transfers between the local filesystem and the forest.
+//! Each transfer keeps going past per-file failures and reports them instead.
+
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use futures::executor::block_on;
+use serde::Serialize;
+use wnfs::common::Metadata;
+use wnfs::private::PrivateNode;
+use wnfsutils::private_forest::PrivateDirectoryHelper;
+
+use crate::nodes;
+use crate::progress::{Cancel, Progress};
+
+pub struct UploadOptions {
+    /// Skip files and directories whose name starts with a dot.
+    pub skip_hidden: bool,
+    /// Replace files that already exist in the forest instead of skipping them.
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Failure {
+    pub path: String,
+    pub error: String,
+    pub code: i32,
+}
+
+/// Paths are relative to the directory being transferred, `/`-separated.
+#[derive(Debug, Default, Serialize)]
+pub struct TransferReport {
+    /// Forest CID after an upload; `None` for downloads, which change nothing.
+    pub cid: Option<String>,
+    pub succeeded: Vec<String>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<Failure>,
+}
+
+impl TransferReport {
+    fn fail(&mut self, path: &[String], error: String, classify: &dyn Fn(&str) -> i32) {
+        let code = classify(&error);
+        self.failed.push(Failure { path: path.join("/"), error, code });
+    }
+}
+
+/// Mirrors `local_dir` under `wnfs_path`, streaming each file, and stores the
+/// forest once at the end. `classify` maps a failure message to an error code
+/// and is called right after the failure so store errors are attributed to the
+/// file that hit them.
+pub fn write_dir_from_path(
+    helper: &mut PrivateDirectoryHelper,
+    wnfs_path: &[String],
+    local_dir: &Path,
+    options: &UploadOptions,
+    classify: &dyn Fn(&str) -> i32,
+) -> Result<TransferReport, String> {
+    if !local_dir.is_dir() {
+        return Err(format!("not a directory: {}", local_dir.display()));
+    }
+    if nodes::exists(helper, wnfs_path)? == Some(nodes::NodeKind::File) {
+        return Err(format!("Expected a directory: {}", wnfs_path.join("/")));
+    }
+    if !wnfs_path.is_empty() {
+        block_on(helper.root_dir.mkdir(wnfs_path, true, Utc::now(), &mut helper.forest, &helper.store, &mut rand::thread_rng()))
+            .map_err(|err| err.to_string())?;
+    }
+
+    let mut report = TransferReport::default();
+    upload_dir(helper, wnfs_path, &[], local_dir, options, &mut report, classify)?;
+    report.cid = Some(nodes::commit(helper)?.to_string());
+    Ok(report)
+}
+
+fn upload_dir(
+    helper: &mut PrivateDirectoryHelper,
+    wnfs_path: &[String],
+    relative: &[String],
+    local_dir: &Path,
+    options: &UploadOptions,
+    report: &mut TransferReport,
+    classify: &dyn Fn(&str) -> i32,
+) -> Result<(), String> {
+    let mut children: Vec<_> = match fs::read_dir(local_dir) {
+        Ok(children) => children.filter_map(|child| child.ok()).collect(),
+        Err(err) => {
+            report.fail(relative, format!("reading {}: {}", local_dir.display(), err), classify);
+            return Ok(());
+        }
+    };
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let name = match child.file_name().into_string() {
+            Ok(name) => name,
+            Err(name) => {
+                let mut path = relative.to_vec();
+                path.push(name.to_string_lossy().into_owned());
+                report.fail(&path, "invalid filename: not valid UTF-8".to_string(), classify);
+                continue;
+            }
+        };
+        if options.skip_hidden && name.starts_with('.') {
+            continue;
+        }
+        let mut child_relative = relative.to_vec();
+        child_relative.push(name);
+        let child_wnfs = [wnfs_path, child_relative.as_slice()].concat();
+        let local = child.path();
+
+        // Symlinks are not followed so a link cycle cannot recurse forever.
+        let file_type = match fs::symlink_metadata(&local) {
+            Ok(meta) => meta.file_type(),
+            Err(err) => {
+                report.fail(&child_relative, format!("reading {}: {}", local.display(), err), classify);
+                continue;
+            }
+        };
+        if file_type.is_dir() {
+            let created = block_on(helper.root_dir.mkdir(
+                &child_wnfs,
+                true,
+                Utc::now(),
+                &mut helper.forest,
+                &helper.store,
+                &mut rand::thread_rng(),
+            ));
+            match created {
+                Ok(()) => upload_dir(helper, wnfs_path, &child_relative, &local, options, report, classify)?,
+                Err(err) => report.fail(&child_relative, err.to_string(), classify),
+            }
+        } else if file_type.is_file() {
+            if !options.overwrite {
+                match nodes::exists(helper, &child_wnfs) {
+                    Ok(None) => {}
+                    Ok(Some(_)) => {
+                        report.skipped.push(child_relative.join("/"));
+                        continue;
+                    }
+                    Err(err) => {
+                        report.fail(&child_relative, err, classify);
+                        continue;
+                    }
+                }
+            }
+            // Writes copy the root and forest on write, so holding on to the
+            // current `Rc`s lets a failed upload put back whatever was there
+            // before: the previous version when overwriting, nothing otherwise.
+            let root_dir = helper.root_dir.clone();
+            let forest = helper.forest.clone();
+            match upload_file(helper, &child_wnfs, &local) {
+                Ok(()) => report.succeeded.push(child_relative.join("/")),
+                Err(err) => {
+                    report.fail(&child_relative, err, classify);
+                    helper.root_dir = root_dir;
+                    helper.forest = forest;
+                }
+            }
+        } else {
+            report.skipped.push(child_relative.join("/"));
+        }
+    }
+    Ok(())
+}
+
+fn upload_file(helper: &mut PrivateDirectoryHelper, wnfs_path: &[String], local: &Path) -> Result<(), String> {
+    let modified: DateTime<Utc> = fs::metadata(local)
+        .and_then(|meta| meta.modified())
+        .map(DateTime::from)
+        .unwrap_or_else(|_| Utc::now());
+    nodes::stream_from_path(helper, wnfs_path, local, modified, &mut Progress::none(), &Cancel::none())
+}
+
+/// Recreates the subtree at `wnfs_path` inside `local_dir`, streaming file
+/// content and carrying each node's modified time over as the local mtime.
+/// Only the modified time is kept: Android and Linux offer no way to set a
+/// file's creation time, so the created time in `Metadata` is not applied.
+/// Entries whose blocks cannot be fetched are reported and the rest of the
+/// tree is still written.
+pub fn read_dir_to_path(
+    helper: &PrivateDirectoryHelper,
+    wnfs_path: &[String],
+    local_dir: &Path,
+    classify: &dyn Fn(&str) -> i32,
+) -> Result<TransferReport, String> {
+    match nodes::exists(helper, wnfs_path)? {
+        Some(nodes::NodeKind::Directory) => {}
+        Some(nodes::NodeKind::File) => return Err(format!("Expected a directory: {}", wnfs_path.join("/"))),
+        None => return Err(format!("Cannot find directory: {}", wnfs_path.join("/"))),
+    }
+    fs::create_dir_all(local_dir).map_err(|err| format!("creating {}: {}", local_dir.display(), err))?;
+
+    let mut report = TransferReport::default();
+    download_dir(helper, wnfs_path, &[], local_dir, &mut report, classify);
+    Ok(report)
+}
+
+fn download_dir(
+    helper: &PrivateDirectoryHelper,
+    wnfs_path: &[String],
+    relative: &[String],
+    local_dir: &Path,
+    report: &mut TransferReport,
+    classify: &dyn Fn(&str) -> i32,
+) {
+    let wnfs_dir = [wnfs_path, relative].concat();
+    let names: Vec<String> = match nodes::get_node(helper, &wnfs_dir) {
+        Ok(Some(PrivateNode::Dir(dir))) => dir.get_entries().cloned().collect(),
+        Ok(_) => return report.fail(relative, format!("Cannot find directory: {}", wnfs_dir.join("/")), classify),
+        Err(err) => return report.fail(relative, err, classify),
+    };
+
+    for name in names {
+        let mut child_relative = relative.to_vec();
+        child_relative.push(name.clone());
+        // Names come from the forest; never let one climb out of local_dir.
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            report.fail(&child_relative, format!("invalid filename: {:?}", name), classify);
+            continue;
+        }
+        let child_wnfs = [wnfs_path, child_relative.as_slice()].concat();
+        let local = local_dir.join(&name);
+
+        match nodes::get_node(helper, &child_wnfs) {
+            Ok(Some(PrivateNode::Dir(dir))) => {
+                if let Err(err) = fs::create_dir_all(&local) {
+                    report.fail(&child_relative, format!("creating {}: {}", local.display(), err), classify);
+                    continue;
+                }
+                download_dir(helper, wnfs_path, &child_relative, &local, report, classify);
+                set_mtime(&local, dir.get_metadata());
+            }
+            Ok(Some(PrivateNode::File(file))) => {
+                let local_name = local.to_string_lossy();
+                match nodes::read_to_path(helper, &child_wnfs, &local_name, 0, &mut Progress::none(), &Cancel::none()) {
+                    Ok(()) => {
+                        set_mtime(&local, file.get_metadata());
+                        report.succeeded.push(child_relative.join("/"));
+                    }
+                    Err(err) => {
+                        report.fail(&child_relative, err, classify);
+                        // A truncated file would look like a finished download.
+                        let _ = fs::remove_file(&local);
+                    }
+                }
+            }
+            Ok(None) => report.fail(&child_relative, format!("Cannot find file or directory: {}", child_wnfs.join("/")), classify),
+            Err(err) => report.fail(&child_relative, err, classify),
+        }
+    }
+}
+
+fn set_mtime(local: &Path, metadata: &Metadata) {
+    if let Some(modified) = metadata.get_modified() {
+        let modified: SystemTime = modified.into();
+        if let Ok(file) = fs::File::open(local) {
+            let _ = file.set_modified(modified);
+        }
+    }
+}
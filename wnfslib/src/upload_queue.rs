@@ -0,0 +1,290 @@
+//! Durable queue of blocks the `Datastore` could not take, so that writes keep
+//! working offline. `QueuedStore` puts a block in the queue when the store
+//! fails, and keeps queueing until the queue is drained: the store must never
+//! receive a block before the blocks it links to. `UploadQueue::drain` sends
+//! the queue children first. Blocks are kept as given; the private ones are
+//! already encrypted and the rest are only forest structure.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Result;
+use kv::{Bucket, Config, Raw, Store};
+use serde::Serialize;
+use wnfsutils::blockstore::FFIStore;
+
+use crate::batch::{links, BlockBatch, BATCH_BLOCKS};
+use crate::dedup::{has_blocks_after, BlockQuery};
+
+pub struct UploadQueue {
+    // Kept open for as long as the buckets are in use.
+    _store: Store,
+    /// Sequence number, big-endian u64 -> block.
+    blocks: Bucket<'static, Raw, Raw>,
+    /// Sequence number, big-endian u64 -> CID. Written after the block, so an
+    /// entry here always has its block.
+    cids: Bucket<'static, Raw, Raw>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    next: u64,
+    bytes: u64,
+    /// Sequence number -> CID, in the order the blocks were queued.
+    queued: BTreeMap<u64, Vec<u8>>,
+    /// CID -> (sequence number, block size).
+    index: HashMap<Vec<u8>, (u64, u64)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingUploads {
+    pub blocks: u64,
+    pub bytes: u64,
+}
+
+impl UploadQueue {
+    /// Opens or creates the queue in `dir`, with whatever an earlier process
+    /// left in it.
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        let store = Store::new(Config::new(dir)).map_err(|err| format!("opening upload queue {}: {}", dir.display(), err))?;
+        let blocks = store.bucket::<Raw, Raw>(Some("blocks")).map_err(|err| err.to_string())?;
+        let cids = store.bucket::<Raw, Raw>(Some("cids")).map_err(|err| err.to_string())?;
+        let queue = Self { _store: store, blocks, cids, state: Mutex::default() };
+
+        {
+            let mut state = queue.state();
+            for item in queue.cids.iter() {
+                let item = item.map_err(|err| err.to_string())?;
+                let key: Raw = item.key().map_err(|err| err.to_string())?;
+                let cid: Raw = item.value().map_err(|err| err.to_string())?;
+                let Some(seq) = decode_seq(&key) else {
+                    continue;
+                };
+                let Some(block) = queue.blocks.get(&key).map_err(|err| err.to_string())? else {
+                    continue;
+                };
+                state.next = state.next.max(seq + 1);
+                state.bytes += block.len() as u64;
+                state.queued.insert(seq, cid.to_vec());
+                state.index.insert(cid.to_vec(), (seq, block.len() as u64));
+            }
+        }
+        Ok(queue)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state().queued.is_empty()
+    }
+
+    pub fn contains(&self, cid: &[u8]) -> bool {
+        self.state().index.contains_key(cid)
+    }
+
+    pub fn get(&self, cid: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let Some(&(seq, _)) = self.state().index.get(cid) else {
+            return Ok(None);
+        };
+        let block = self.blocks.get(&Raw::from(seq.to_be_bytes().as_slice())).map_err(|err| err.to_string())?;
+        Ok(block.map(|block| block.to_vec()))
+    }
+
+    /// Appends the blocks not already queued and waits until they are on disk.
+    pub fn push(&self, blocks: &[(Vec<u8>, Vec<u8>)]) -> Result<(), String> {
+        let mut state = self.state();
+        for (cid, bytes) in blocks {
+            if state.index.contains_key(cid) {
+                continue;
+            }
+            let seq = state.next;
+            let key = Raw::from(seq.to_be_bytes().as_slice());
+            self.blocks.set(&key, &Raw::from(bytes.as_slice())).map_err(|err| err.to_string())?;
+            self.cids.set(&key, &Raw::from(cid.as_slice())).map_err(|err| err.to_string())?;
+            state.next += 1;
+            state.bytes += bytes.len() as u64;
+            state.queued.insert(seq, cid.clone());
+            state.index.insert(cid.clone(), (seq, bytes.len() as u64));
+        }
+        self.blocks.flush().map_err(|err| err.to_string())?;
+        self.cids.flush().map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    pub fn pending(&self) -> PendingUploads {
+        let state = self.state();
+        PendingUploads { blocks: state.queued.len() as u64, bytes: state.bytes }
+    }
+
+    /// Sends the queued blocks to `store`, each after the queued blocks it
+    /// links to, and returns how many were sent. Every batch leaves the queue
+    /// once the store has taken it, so a failure keeps only what was not sent.
+    /// The queue is not locked while a batch is sent; blocks queued meanwhile
+    /// wait for the next drain.
+    pub fn drain<'a, S: BlockBatch<'a>>(&self, store: &S) -> Result<u64, String> {
+        let order = self.dependency_order(&self.state())?;
+        let mut sent = 0;
+        for chunk in order.chunks(BATCH_BLOCKS) {
+            let mut seqs = Vec::with_capacity(chunk.len());
+            let mut blocks = Vec::with_capacity(chunk.len());
+            {
+                let state = self.state();
+                for &seq in chunk {
+                    // Already sent by a drain running alongside this one.
+                    let Some(cid) = state.queued.get(&seq) else {
+                        continue;
+                    };
+                    let key = Raw::from(seq.to_be_bytes().as_slice());
+                    let block = self.blocks.get(&key).map_err(|err| err.to_string())?;
+                    let block = block.ok_or_else(|| format!("queued block {} is missing", seq))?;
+                    seqs.push(seq);
+                    blocks.push((cid.clone(), block.to_vec()));
+                }
+            }
+            if blocks.is_empty() {
+                continue;
+            }
+            store.put_blocks(blocks).map_err(|err| err.to_string())?;
+
+            let mut state = self.state();
+            for &seq in &seqs {
+                let key = Raw::from(seq.to_be_bytes().as_slice());
+                self.cids.remove(&key).map_err(|err| err.to_string())?;
+                self.blocks.remove(&key).map_err(|err| err.to_string())?;
+                if let Some(cid) = state.queued.remove(&seq) {
+                    if let Some((_, size)) = state.index.remove(&cid) {
+                        state.bytes -= size;
+                    }
+                }
+            }
+            // CIDs first, the reverse of `push`: a crash in between leaves
+            // blocks with no CID, which are never read, rather than the other way round.
+            self.cids.flush().map_err(|err| err.to_string())?;
+            self.blocks.flush().map_err(|err| err.to_string())?;
+            sent += seqs.len() as u64;
+        }
+        Ok(sent)
+    }
+
+    /// Sequence numbers of every queued block in post-order: queued blocks
+    /// come after the queued blocks they link to, and otherwise in the order
+    /// they were queued.
+    fn dependency_order(&self, state: &State) -> Result<Vec<u64>, String> {
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (&seq, cid) in &state.queued {
+            let key = Raw::from(seq.to_be_bytes().as_slice());
+            let Some(block) = self.blocks.get(&key).map_err(|err| err.to_string())? else {
+                continue;
+            };
+            let linked = links(cid, &block)
+                .into_iter()
+                .filter_map(|link| state.index.get(&link.to_bytes()).map(|&(child, _)| child))
+                .collect();
+            children.insert(seq, linked);
+        }
+
+        let mut order = Vec::with_capacity(state.queued.len());
+        let mut expanded = HashSet::new();
+        for &root in state.queued.keys() {
+            // (sequence number, whether its links have already been pushed)
+            let mut stack = vec![(root, false)];
+            while let Some((seq, done)) = stack.pop() {
+                if done {
+                    order.push(seq);
+                    continue;
+                }
+                if !expanded.insert(seq) {
+                    continue;
+                }
+                stack.push((seq, true));
+                for &child in children.get(&seq).into_iter().flatten().rev() {
+                    if !expanded.contains(&child) {
+                        stack.push((child, false));
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn decode_seq(key: &[u8]) -> Option<u64> {
+    Some(u64::from_be_bytes(key.try_into().ok()?))
+}
+
+/// Passes blocks to `inner` until a put fails in a way `offline` accepts, and
+/// from then on queues them until the queue is drained. Reads of queued
+/// blocks are served from the queue. Without a queue every call goes straight
+/// to `inner`.
+#[derive(Clone)]
+pub struct QueuedStore<S> {
+    inner: S,
+    queue: Option<Arc<UploadQueue>>,
+    /// Whether a failed put means the store is unreachable, rather than
+    /// broken, so the blocks are worth queueing.
+    offline: fn(&anyhow::Error) -> bool,
+}
+
+impl<S> QueuedStore<S> {
+    pub fn new(inner: S, queue: Option<Arc<UploadQueue>>, offline: fn(&anyhow::Error) -> bool) -> Self {
+        Self { inner, queue, offline }
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a> FFIStore<'a> for QueuedStore<S> {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        if let Some(queue) = &self.queue {
+            if let Some(block) = queue.get(&cid).map_err(anyhow::Error::msg)? {
+                return Ok(block);
+            }
+        }
+        self.inner.get_block(cid)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        self.put_blocks(vec![(cid, bytes)])
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + Clone + 'a> BlockBatch<'a> for QueuedStore<S> {
+    fn get_blocks(&self, cids: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        let Some(queue) = &self.queue else {
+            return self.inner.get_blocks(cids);
+        };
+        if !cids.iter().any(|cid| queue.contains(cid)) {
+            return self.inner.get_blocks(cids);
+        }
+        cids.into_iter().map(|cid| self.get_block(cid)).collect()
+    }
+
+    fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        let Some(queue) = &self.queue else {
+            return self.inner.put_blocks(blocks);
+        };
+        if queue.is_empty() {
+            match self.inner.put_blocks(blocks.clone()) {
+                Err(err) if (self.offline)(&err) => {}
+                result => return result,
+            }
+        }
+        queue.push(&blocks).map_err(anyhow::Error::msg)
+    }
+}
+
+impl<'a, S: BlockBatch<'a> + BlockQuery<'a> + Clone + 'a> BlockQuery<'a> for QueuedStore<S> {
+    fn has_block(&self, cid: &[u8]) -> Result<Option<bool>> {
+        if self.queue.as_ref().is_some_and(|queue| queue.contains(cid)) {
+            return Ok(Some(true));
+        }
+        self.inner.has_block(cid)
+    }
+
+    fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+        has_blocks_after(&self.inner, cids, |cid| self.queue.as_ref().is_some_and(|queue| queue.contains(cid)))
+    }
+}
@@ -0,0 +1,149 @@
+/* Exercises include/wnfs.h from C against an in-memory block store. Built and
+ * run by tests/c_abi.rs; exits non-zero on the first failed check. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "wnfs.h"
+
+#define CHECK(cond)                                                          \
+  do {                                                                       \
+    if (!(cond)) {                                                           \
+      const char *reason = wnfs_last_error_message();                        \
+      fprintf(stderr, "%s:%d: check failed: %s (%s)\n", __FILE__, __LINE__,  \
+              #cond, reason ? reason : "no error");                          \
+      return 1;                                                              \
+    }                                                                        \
+  } while (0)
+
+typedef struct Block {
+  uint8_t *cid;
+  size_t cid_len;
+  uint8_t *data;
+  size_t data_len;
+  struct Block *next;
+} Block;
+
+typedef struct MemoryStore {
+  Block *blocks;
+  int offline;
+  int released;
+} MemoryStore;
+
+static bool get_block(void *context, const uint8_t *cid, size_t cid_len, WnfsBuffer *out) {
+  MemoryStore *store = context;
+  if (store->offline) {
+    return false;
+  }
+  for (Block *block = store->blocks; block; block = block->next) {
+    if (block->cid_len == cid_len && memcmp(block->cid, cid, cid_len) == 0) {
+      out->data = block->data;
+      out->len = block->data_len;
+      return true;
+    }
+  }
+  return false;
+}
+
+static bool put_block(void *context, const uint8_t *cid, size_t cid_len, const uint8_t *data, size_t data_len) {
+  MemoryStore *store = context;
+  Block *block = malloc(sizeof(Block));
+  block->cid = malloc(cid_len);
+  block->data = malloc(data_len ? data_len : 1);
+  memcpy(block->cid, cid, cid_len);
+  memcpy(block->data, data, data_len);
+  block->cid_len = cid_len;
+  block->data_len = data_len;
+  block->next = store->blocks;
+  store->blocks = block;
+  return true;
+}
+
+static void release_block(void *context, WnfsBuffer buffer) {
+  (void)buffer;
+  ((MemoryStore *)context)->released++;
+}
+
+static void free_store(MemoryStore *store) {
+  while (store->blocks) {
+    Block *next = store->blocks->next;
+    free(store->blocks->cid);
+    free(store->blocks->data);
+    free(store->blocks);
+    store->blocks = next;
+  }
+}
+
+static int contains(WnfsBuffer buffer, const char *text) {
+  size_t len = strlen(text);
+  for (size_t i = 0; i + len <= buffer.len; i++) {
+    if (memcmp(buffer.data + i, text, len) == 0) {
+      return 1;
+    }
+  }
+  return 0;
+}
+
+int main(void) {
+  MemoryStore memory = {0};
+  WnfsBlockStore store = {&memory, get_block, put_block, release_block};
+  uint8_t key[32];
+  for (int i = 0; i < 32; i++) {
+    key[i] = (uint8_t)i;
+  }
+
+  WnfsDrive *drive = NULL;
+  char *cid = NULL;
+  CHECK(wnfs_init(store, key, sizeof key, &drive, &cid));
+  CHECK(drive != NULL && cid != NULL);
+  wnfs_string_free(cid);
+
+  CHECK(wnfs_mkdir(drive, "root/docs", NULL));
+  const char *hello = "hello from C";
+  CHECK(wnfs_write(drive, "root/docs/a.txt", (const uint8_t *)hello, strlen(hello), NULL));
+  CHECK(wnfs_cp(drive, "root/docs/a.txt", "root/docs/b.txt", NULL));
+  CHECK(wnfs_mv(drive, "root/docs/b.txt", "root/c.txt", &cid));
+
+  WnfsBuffer content = {0};
+  CHECK(wnfs_read(drive, "root/c.txt", &content));
+  CHECK(content.len == strlen(hello) && memcmp(content.data, hello, content.len) == 0);
+  wnfs_buffer_free(content);
+
+  WnfsBuffer json = {0};
+  CHECK(wnfs_ls(drive, "root/docs", &json));
+  CHECK(contains(json, "\"name\":\"a.txt\"") && !contains(json, "b.txt"));
+  wnfs_buffer_free(json);
+  CHECK(wnfs_stat(drive, "root/c.txt", &json));
+  CHECK(contains(json, "\"kind\":\"file\""));
+  wnfs_buffer_free(json);
+
+  WnfsNodeKind kind = WNFS_NODE_KIND_NONE;
+  CHECK(wnfs_exists(drive, "root/docs", &kind) && kind == WNFS_NODE_KIND_DIRECTORY);
+  CHECK(wnfs_rm(drive, "root/docs", NULL));
+  CHECK(wnfs_exists(drive, "root/docs", &kind) && kind == WNFS_NODE_KIND_NONE);
+
+  CHECK(!wnfs_read(drive, "root/missing.txt", &content));
+  CHECK(wnfs_last_error_code() == WNFS_ERROR_CODE_NOT_FOUND);
+  CHECK(wnfs_last_error_message() != NULL);
+  wnfs_drive_free(drive);
+
+  /* A second drive loaded from the root CID sees the same tree. */
+  drive = NULL;
+  CHECK(wnfs_load(store, cid, key, sizeof key, &drive));
+  CHECK(wnfs_read(drive, "root/c.txt", &content));
+  CHECK(content.len == strlen(hello));
+  wnfs_buffer_free(content);
+  CHECK(memory.released > 0);
+  wnfs_drive_free(drive);
+
+  memory.offline = 1;
+  CHECK(!wnfs_load(store, cid, key, sizeof key, &drive));
+  CHECK(wnfs_last_error_code() == WNFS_ERROR_CODE_STORE_UNAVAILABLE);
+  CHECK(!wnfs_load(store, "not a cid", key, sizeof key, &drive));
+  CHECK(wnfs_last_error_code() == WNFS_ERROR_CODE_INVALID_CID);
+
+  wnfs_string_free(cid);
+  free_store(&memory);
+  printf("ok\n");
+  return 0;
+}
@@ -0,0 +1,40 @@
+//! Builds `tests/c/wnfs_test.c` against the `wnfs.h` generated by `build.rs`
+//! and the cdylib cargo produced for this test run, then runs it. Needs a C
+//! compiler (`CC` or `cc`).
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_program_uses_the_c_abi() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // The cdylib is built next to the test binaries, in target/<profile>/deps.
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("wnfs_test");
+
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(cc)
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(env!("WNFS_INCLUDE_DIR"))
+        .arg(manifest.join("tests/c/wnfs_test.c"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lwnfslib")
+        .arg("-o")
+        .arg(&out)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "compiling tests/c/wnfs_test.c failed");
+
+    let output = Command::new(&out).output().unwrap();
+    assert!(
+        output.status.success(),
+        "wnfs_test failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
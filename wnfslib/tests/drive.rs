@@ -0,0 +1,633 @@
+//! Exercises `WnfsDrive` directly over an in-memory `FFIStore`, without a JVM.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use libipld::cbor::DagCborCodec;
+use libipld::codec::Codec;
+use libipld::multihash::{Code, MultihashDigest};
+use libipld::{Cid, Ipld};
+use wnfslib::core::{
+    export_car, BatchedStore, BlockBatch, BlockCache, BlockQuery, CachedStore, Cancel, DedupStore, DiskCache,
+    DiskCachedStore, FFIStore, NodeKind, Op, Progress, PutPolicy, PutStats, QueuedStore, UploadOptions, UploadQueue,
+    WnfsDrive, BATCH_BLOCKS, CANCELLED,
+};
+
+#[derive(Clone, Default)]
+struct MemoryStore {
+    blocks: Rc<RefCell<HashMap<Vec<u8>, Vec<u8>>>>,
+}
+
+impl<'a> FFIStore<'a> for MemoryStore {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        self.blocks.borrow().get(&cid).cloned().ok_or_else(|| anyhow!("block not available"))
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        self.blocks.borrow_mut().insert(cid, bytes);
+        Ok(())
+    }
+}
+
+impl<'a> BlockBatch<'a> for MemoryStore {}
+
+impl<'a> BlockQuery<'a> for MemoryStore {
+    fn has_block(&self, cid: &[u8]) -> Result<Option<bool>> {
+        Ok(Some(self.blocks.borrow().contains_key(cid)))
+    }
+}
+
+/// Records the size of every batch it is handed, and of every `has_blocks` query.
+#[derive(Clone, Default)]
+struct BatchingStore {
+    store: MemoryStore,
+    batches: Rc<RefCell<Vec<usize>>>,
+    queries: Rc<RefCell<Vec<usize>>>,
+}
+
+impl<'a> FFIStore<'a> for BatchingStore {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        self.store.get_block(cid)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        self.batches.borrow_mut().push(1);
+        self.store.put_block(cid, bytes)
+    }
+}
+
+impl<'a> BlockBatch<'a> for BatchingStore {
+    fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        self.batches.borrow_mut().push(blocks.len());
+        blocks.into_iter().try_for_each(|(cid, bytes)| self.store.put_block(cid, bytes))
+    }
+}
+
+impl<'a> BlockQuery<'a> for BatchingStore {
+    fn has_blocks(&self, cids: &[Vec<u8>]) -> Result<Vec<Option<bool>>> {
+        self.queries.borrow_mut().push(cids.len());
+        cids.iter().map(|cid| self.store.has_block(cid)).collect()
+    }
+}
+
+/// Fails every put while offline, and checks that every block it takes only
+/// links to blocks it already holds.
+#[derive(Clone, Default)]
+struct FlakyStore {
+    store: MemoryStore,
+    offline: Rc<Cell<bool>>,
+}
+
+impl<'a> FFIStore<'a> for FlakyStore {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        self.store.get_block(cid)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        if self.offline.get() {
+            return Err(anyhow!("store unavailable: offline"));
+        }
+        let block = Cid::try_from(cid.as_slice())?;
+        if block.codec() == u64::from(DagCborCodec) {
+            let mut links = Vec::new();
+            DagCborCodec.decode::<Ipld>(&bytes)?.references(&mut links);
+            for link in links {
+                let stored = self.store.blocks.borrow().contains_key(&link.to_bytes());
+                assert!(stored, "{} stored before its child {}", block, link);
+            }
+        }
+        self.store.put_block(cid, bytes)
+    }
+}
+
+impl<'a> BlockBatch<'a> for FlakyStore {}
+
+impl<'a> BlockQuery<'a> for FlakyStore {}
+
+/// Refuses blocks larger than `limit`, so file content fails to upload while
+/// the small directory and forest blocks still go through.
+#[derive(Clone, Default)]
+struct LimitedStore {
+    store: MemoryStore,
+    limit: usize,
+}
+
+impl<'a> FFIStore<'a> for LimitedStore {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        self.store.get_block(cid)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        if bytes.len() > self.limit {
+            return Err(anyhow!("block too large: {} bytes", bytes.len()));
+        }
+        self.store.put_block(cid, bytes)
+    }
+}
+
+impl<'a> BlockBatch<'a> for LimitedStore {}
+
+impl<'a> BlockQuery<'a> for LimitedStore {}
+
+/// Queues a block of its own while taking a batch, like a write made while
+/// the queue is being synced.
+#[derive(Clone)]
+struct QueueingStore<'q> {
+    store: FlakyStore,
+    queue: &'q UploadQueue,
+    late: (Vec<u8>, Vec<u8>),
+}
+
+impl<'a, 'q: 'a> FFIStore<'a> for QueueingStore<'q> {
+    fn get_block(&self, cid: Vec<u8>) -> Result<Vec<u8>> {
+        self.store.get_block(cid)
+    }
+
+    fn put_block(&self, cid: Vec<u8>, bytes: Vec<u8>) -> Result<()> {
+        self.put_blocks(vec![(cid, bytes)])
+    }
+}
+
+impl<'a, 'q: 'a> BlockBatch<'a> for QueueingStore<'q> {
+    fn put_blocks(&self, blocks: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        self.queue.push(&[self.late.clone()]).map_err(anyhow::Error::msg)?;
+        self.store.put_blocks(blocks)
+    }
+}
+
+fn is_offline(err: &anyhow::Error) -> bool {
+    err.to_string().starts_with("store unavailable")
+}
+
+fn wnfs_key() -> Vec<u8> {
+    (0..32).collect()
+}
+
+fn path(path: &str) -> Vec<String> {
+    WnfsDrive::parse_path(path)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Reads a CARv1 file back into its root and a store holding its blocks.
+fn read_car(path: &std::path::Path) -> (Cid, MemoryStore) {
+    fn varint(bytes: &[u8], position: &mut usize) -> usize {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = bytes[*position];
+            *position += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    let bytes = std::fs::read(path).unwrap();
+    let mut position = 0;
+    let header_len = varint(&bytes, &mut position);
+    let header: Ipld = DagCborCodec.decode(&bytes[position..position + header_len]).unwrap();
+    position += header_len;
+    let roots = match header.get("roots").unwrap() {
+        Ipld::List(roots) => roots.clone(),
+        other => panic!("unexpected roots {:?}", other),
+    };
+    assert_eq!(header.get("version").unwrap(), &Ipld::Integer(1));
+    let root = match roots.as_slice() {
+        [Ipld::Link(root)] => *root,
+        other => panic!("unexpected roots {:?}", other),
+    };
+
+    let store = MemoryStore::default();
+    while position < bytes.len() {
+        let len = varint(&bytes, &mut position);
+        let section = &bytes[position..position + len];
+        let cid = Cid::read_bytes(section).unwrap();
+        let cid_len = cid.to_bytes().len();
+        store.put_block(cid.to_bytes(), section[cid_len..].to_vec()).unwrap();
+        position += len;
+    }
+    (root, store)
+}
+
+#[test]
+fn init_write_and_reload() {
+    let store = MemoryStore::default();
+    let (mut drive, _) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    drive.mkdir(&path("root/docs")).unwrap();
+    let cid = drive.write(&path("root/docs/a.txt"), b"hello".to_vec()).unwrap();
+
+    let mut loaded = WnfsDrive::load(store.clone(), cid, wnfs_key()).unwrap();
+    assert_eq!(loaded.read(&path("root/docs/a.txt")).unwrap(), b"hello");
+
+    let reloaded = WnfsDrive::reload(store, cid).unwrap();
+    let names: Vec<_> = reloaded.ls(&path("root/docs")).unwrap().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["a.txt"]);
+}
+
+#[test]
+fn stat_and_ranges() {
+    let (mut drive, _) = WnfsDrive::init(MemoryStore::default(), wnfs_key()).unwrap();
+    drive.write(&path("root/a.txt"), b"0123456789".to_vec()).unwrap();
+
+    let info = drive.stat(&path("root/a.txt")).unwrap();
+    assert_eq!(info.kind, NodeKind::File);
+    assert_eq!(info.size, 10);
+    assert_eq!(drive.exists(&path("root")).unwrap(), Some(NodeKind::Directory));
+    assert_eq!(drive.exists(&path("root/missing")).unwrap(), None);
+
+    assert_eq!(drive.read_range(&path("root/a.txt"), 2, Some(3)).unwrap(), b"234");
+    assert_eq!(drive.read_range(&path("root/a.txt"), 7, None).unwrap(), b"789");
+}
+
+#[test]
+fn ls_and_stat_do_not_put() {
+    let store = BatchingStore::default();
+    let (mut drive, _) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    drive.write(&path("root/a.txt"), b"a".to_vec()).unwrap();
+    store.batches.borrow_mut().clear();
+
+    let entries = drive.ls(&path("root")).unwrap();
+    assert!(entries[0].info.cid.is_some());
+    assert!(drive.stat(&path("root/a.txt")).unwrap().cid.is_some());
+    assert!(store.batches.borrow().is_empty());
+}
+
+#[test]
+fn resumed_download_checks_offset() {
+    let (mut drive, _) = WnfsDrive::init(MemoryStore::default(), wnfs_key()).unwrap();
+    drive.write(&path("root/a.txt"), b"0123456789".to_vec()).unwrap();
+    let local = temp_dir("resume").join("a.txt");
+    let local_name = local.to_str().unwrap();
+    let mut resume = |offset| {
+        let mut progress = Progress::none();
+        drive.read_stream_to_path(&path("root/a.txt"), local_name, offset, &mut progress, &Cancel::none())
+    };
+
+    std::fs::write(&local, b"0123").unwrap();
+    let err = resume(6).unwrap_err();
+    assert!(err.contains("only holds 4 bytes"), "{}", err);
+    let err = resume(11).unwrap_err();
+    assert!(err.contains("past the end"), "{}", err);
+    assert_eq!(std::fs::read(&local).unwrap(), b"0123");
+
+    resume(4).unwrap();
+    assert_eq!(std::fs::read(&local).unwrap(), b"0123456789");
+}
+
+#[test]
+fn upload_cancelled_mid_stream() {
+    let store = MemoryStore::default();
+    let (mut drive, _) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    let cid = drive.write(&path("root/a.txt"), b"old".to_vec()).unwrap();
+    let local = temp_dir("cancel").join("a.txt");
+    std::fs::write(&local, vec![7; 4 * 1024 * 1024]).unwrap();
+
+    // Trips once the first chunk has been read.
+    let processed = Rc::new(Cell::new(0));
+    let seen = processed.clone();
+    let mut progress = Progress::new(move |done, _| seen.set(done), Duration::ZERO);
+    let cancel = Cancel::new(|| processed.get() > 0);
+    let err = drive
+        .write_stream_from_path(&path("root/a.txt"), local.to_str().unwrap(), &mut progress, &cancel)
+        .unwrap_err();
+    assert_eq!(err, CANCELLED);
+    assert!(processed.get() < 4 * 1024 * 1024);
+
+    let mut drive = WnfsDrive::load(store, cid, wnfs_key()).unwrap();
+    assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"old");
+}
+
+#[test]
+fn mv_cp_rm_and_transactions() {
+    let (mut drive, _) = WnfsDrive::init(MemoryStore::default(), wnfs_key()).unwrap();
+    drive.write(&path("root/a.txt"), b"a".to_vec()).unwrap();
+    drive.cp(&path("root/a.txt"), &path("root/b.txt")).unwrap();
+    drive.mv(&path("root/a.txt"), &path("root/c.txt")).unwrap();
+    drive.rm(&path("root/b.txt")).unwrap();
+    let names: Vec<_> = drive.ls(&path("root")).unwrap().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["c.txt"]);
+
+    drive
+        .apply(vec![
+            Op::Mkdir { path: path("root/t") },
+            Op::WriteFile { path: path("root/t/x"), content: b"x".to_vec() },
+        ])
+        .unwrap();
+    assert_eq!(drive.read(&path("root/t/x")).unwrap(), b"x");
+
+    let err = drive.apply(vec![Op::Rm { path: path("root/nope") }]).unwrap_err();
+    assert!(err.contains("transaction step 0"), "{}", err);
+}
+
+#[test]
+fn failed_transaction_leaves_the_drive_unchanged() {
+    let store = MemoryStore::default();
+    let (mut drive, _) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    drive.write(&path("root/a.txt"), b"a".to_vec()).unwrap();
+
+    let err = drive
+        .apply(vec![
+            Op::WriteFile { path: path("root/a.txt"), content: b"changed".to_vec() },
+            Op::Rm { path: path("root/nope") },
+        ])
+        .unwrap_err();
+    assert!(err.contains("transaction step 1"), "{}", err);
+    let names: Vec<_> = drive.ls(&path("root")).unwrap().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["a.txt"]);
+    assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"a");
+
+    // The next commit must not carry the first step along either.
+    let cid = drive.mkdir(&path("root/docs")).unwrap();
+    let mut drive = WnfsDrive::load(store, cid, wnfs_key()).unwrap();
+    assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"a");
+}
+
+#[test]
+fn failed_overwrite_keeps_the_previous_version() {
+    let store = LimitedStore { limit: 4096, ..Default::default() };
+    let (mut drive, _) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    drive.write(&path("root/up/a.txt"), b"old".to_vec()).unwrap();
+    let local = temp_dir("failed-overwrite");
+    std::fs::write(local.join("a.txt"), vec![7; 64 * 1024]).unwrap();
+    std::fs::write(local.join("b.txt"), b"new").unwrap();
+
+    let options = UploadOptions { skip_hidden: false, overwrite: true };
+    let report = drive.write_dir_from_path(&path("root/up"), &local, &options, &|_: &str| -1).unwrap();
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].path, "a.txt");
+    assert_eq!(report.succeeded, ["b.txt"]);
+    assert_eq!(drive.read(&path("root/up/a.txt")).unwrap(), b"old");
+
+    let cid = Cid::try_from(report.cid.unwrap().as_str()).unwrap();
+    let mut drive = WnfsDrive::load(store, cid, wnfs_key()).unwrap();
+    assert_eq!(drive.read(&path("root/up/a.txt")).unwrap(), b"old");
+    assert_eq!(drive.read(&path("root/up/b.txt")).unwrap(), b"new");
+}
+
+#[test]
+fn block_cache_evicts_least_recently_used() {
+    let mut cache = BlockCache::new(30);
+    cache.insert(b"a".to_vec(), vec![0; 9]);
+    cache.insert(b"b".to_vec(), vec![0; 9]);
+    cache.insert(b"c".to_vec(), vec![0; 9]);
+    assert!(cache.get(b"a").is_some());
+    cache.insert(b"d".to_vec(), vec![0; 9]);
+    assert!(cache.get(b"b").is_none());
+    assert!(cache.get(b"a").is_some());
+    // Larger than the whole cache: never kept.
+    cache.insert(b"e".to_vec(), vec![0; 40]);
+    assert!(cache.get(b"e").is_none());
+
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.size, stats.hits, stats.misses), (3, 30, 2, 2));
+}
+
+#[test]
+fn cached_store_serves_repeat_reads() {
+    let store = MemoryStore::default();
+    let (mut drive, _) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    let cid = drive.write(&path("root/a.txt"), b"hello".to_vec()).unwrap();
+
+    let cache = Arc::new(Mutex::new(BlockCache::new(1 << 20)));
+    let mut drive = WnfsDrive::load(CachedStore::new(store.clone(), cache.clone()), cid, wnfs_key()).unwrap();
+    drive.read(&path("root/a.txt")).unwrap();
+    store.blocks.borrow_mut().clear();
+    assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"hello");
+    assert!(cache.lock().unwrap().stats().hits > 0);
+}
+
+#[test]
+fn disk_cache_survives_reopening() {
+    let dir = temp_dir("disk-cache");
+    let store = MemoryStore::default();
+    let cid = {
+        let cache = Arc::new(DiskCache::open(&dir, 1 << 20, b"cache key").unwrap());
+        let (mut drive, _) = WnfsDrive::init(DiskCachedStore::new(store.clone(), Some(cache)), wnfs_key()).unwrap();
+        drive.write(&path("root/a.txt"), b"hello".to_vec()).unwrap()
+    };
+    store.blocks.borrow_mut().clear();
+
+    let cache = Arc::new(DiskCache::open(&dir, 1 << 20, b"cache key").unwrap());
+    assert!(cache.stats().entries > 0);
+    let mut drive = WnfsDrive::load(DiskCachedStore::new(store.clone(), Some(cache.clone())), cid, wnfs_key()).unwrap();
+    assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"hello");
+    assert_eq!(cache.stats().misses, 0);
+
+    cache.clear().unwrap();
+    assert_eq!(cache.stats().entries, 0);
+    assert!(WnfsDrive::load(DiskCachedStore::new(store, Some(cache)), cid, wnfs_key()).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn disk_cache_ignores_other_keys_and_evicts() {
+    let dir = temp_dir("disk-cache-keys");
+    {
+        let cache = DiskCache::open(&dir, 1 << 20, b"first key").unwrap();
+        cache.insert(b"a", b"block a");
+        assert_eq!(cache.get(b"a").unwrap(), b"block a");
+    }
+    {
+        let cache = DiskCache::open(&dir, 1 << 20, b"second key").unwrap();
+        assert!(cache.get(b"a").is_none());
+    }
+
+    // Each entry costs a 32-byte name, 28 bytes of nonce and tag, and the block.
+    let cache = DiskCache::open(&dir, 3 * 70, b"first key").unwrap();
+    cache.clear().unwrap();
+    for cid in [b"b", b"c", b"d"] {
+        cache.insert(cid, &[0; 10]);
+    }
+    assert!(cache.get(b"b").is_some());
+    cache.insert(b"e", &[0; 10]);
+    assert!(cache.get(b"c").is_none());
+    assert!(cache.get(b"b").is_some());
+    assert_eq!(cache.stats().entries, 3);
+    drop(cache);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn batched_store_flushes_when_operations_commit() {
+    let store = BatchingStore::default();
+    let batched = BatchedStore::new(store.clone());
+    let (drive, _) = WnfsDrive::init(batched.clone(), wnfs_key()).unwrap();
+    let mut drive = drive.with_batch(batched);
+    drive.send_pending().unwrap();
+
+    let content: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let cid = drive.write(&path("root/big.bin"), content.clone()).unwrap();
+    // Everything the new root needs is stored by the time its CID is returned.
+    let mut loaded = WnfsDrive::load(store.store.clone(), cid, wnfs_key()).unwrap();
+    assert_eq!(loaded.read(&path("root/big.bin")).unwrap(), content);
+
+    let batches = store.batches.borrow();
+    assert!(batches.iter().all(|&size| size <= BATCH_BLOCKS));
+    assert!(batches.len() < store.store.blocks.borrow().len());
+}
+
+#[test]
+fn dedup_store_skips_blocks_already_stored() {
+    let store = MemoryStore::default();
+    let stats = Arc::new(PutStats::default());
+    let always = DedupStore::new(store.clone(), PutPolicy::Always, None, stats.clone());
+    always.put_block(b"a".to_vec(), vec![1; 10]).unwrap();
+    always.put_block(b"a".to_vec(), vec![1; 10]).unwrap();
+    let checking = DedupStore::new(store.clone(), PutPolicy::CheckFirst, None, stats.clone());
+    checking.put_block(b"a".to_vec(), vec![1; 10]).unwrap();
+    checking.put_block(b"b".to_vec(), vec![2; 5]).unwrap();
+    let counters = stats.counters();
+    assert_eq!(
+        (counters.puts, counters.put_bytes, counters.checks, counters.skipped, counters.skipped_bytes),
+        (3, 25, 2, 1, 10)
+    );
+
+    // A block in the persistent cache is skipped without asking the store.
+    let dir = temp_dir("dedup");
+    let cache = Arc::new(DiskCache::open(&dir, 1 << 20, b"cache key").unwrap());
+    cache.insert(b"c", &[3; 7]);
+    let trusting = DedupStore::new(store.clone(), PutPolicy::TrustCache, Some(cache), stats.clone());
+    trusting.put_block(b"c".to_vec(), vec![3; 7]).unwrap();
+    trusting.put_block(b"d".to_vec(), vec![4; 3]).unwrap();
+    assert!(!store.blocks.borrow().contains_key(b"c".as_slice()));
+    assert!(store.blocks.borrow().contains_key(b"d".as_slice()));
+    let counters = stats.counters();
+    assert_eq!((counters.checks, counters.skipped, counters.skipped_bytes), (3, 2, 17));
+    drop(trusting);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dedup_checks_flushed_blocks_a_batch_at_a_time() {
+    let store = BatchingStore::default();
+    let (drive, cid) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    drop(drive);
+    let stored = store.store.blocks.borrow().len();
+
+    let stats = Arc::new(PutStats::default());
+    let batched = BatchedStore::write_back(DedupStore::new(store.clone(), PutPolicy::CheckFirst, None, stats.clone()));
+    let mut drive = WnfsDrive::load(batched.clone(), cid, wnfs_key()).unwrap().with_batch(batched.clone());
+    for draft in ["one", "two", "three"] {
+        drive.write(&path("root/a.txt"), draft.as_bytes().to_vec()).unwrap();
+    }
+    assert!(store.queries.borrow().is_empty());
+    drive.flush().unwrap();
+
+    // Only the blocks of the final state are checked, with one query per batch.
+    let queries = store.queries.borrow();
+    let counters = stats.counters();
+    assert_eq!(counters.checks, queries.iter().sum::<usize>() as u64);
+    assert_eq!(counters.checks, counters.puts + counters.skipped);
+    assert_eq!(counters.puts as usize, store.store.blocks.borrow().len() - stored);
+    assert!(queries.len() < counters.checks as usize, "{:?}", queries);
+}
+
+#[test]
+fn write_back_sends_only_the_final_state() {
+    let store = MemoryStore::default();
+    let (drive, cid) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    drop(drive);
+    let stored = store.blocks.borrow().len();
+
+    let batched = BatchedStore::write_back(store.clone());
+    let mut drive = WnfsDrive::load(batched.clone(), cid, wnfs_key()).unwrap().with_batch(batched.clone());
+    for draft in ["one", "two", "three"] {
+        drive.write(&path("root/a.txt"), draft.as_bytes().to_vec()).unwrap();
+    }
+    drive.mkdir(&path("root/docs")).unwrap();
+    assert_eq!(store.blocks.borrow().len(), stored);
+    assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"three");
+
+    let root = drive.flush().unwrap();
+    assert_eq!(batched.pending_bytes(), 0);
+    let mut loaded = WnfsDrive::load(store.clone(), root, wnfs_key()).unwrap();
+    assert_eq!(loaded.read(&path("root/a.txt")).unwrap(), b"three");
+    assert!(loaded.exists(&path("root/docs")).unwrap().is_some());
+}
+
+#[test]
+fn upload_queue_keeps_offline_writes_until_synced() {
+    let dir = temp_dir("upload-queue");
+    let flaky = FlakyStore::default();
+    let (drive, cid) = WnfsDrive::init(flaky.clone(), wnfs_key()).unwrap();
+    drop(drive);
+    let stored = flaky.store.blocks.borrow().len();
+
+    let root = {
+        let queue = Arc::new(UploadQueue::open(&dir).unwrap());
+        let batched = BatchedStore::new(QueuedStore::new(flaky.clone(), Some(queue.clone()), is_offline));
+        let mut drive = WnfsDrive::load(batched.clone(), cid, wnfs_key()).unwrap().with_batch(batched);
+        flaky.offline.set(true);
+        drive.write(&path("root/a.txt"), b"offline".to_vec()).unwrap();
+        // Back online, but later blocks still wait behind the queued ones.
+        flaky.offline.set(false);
+        let root = drive.write(&path("root/b.txt"), b"online".to_vec()).unwrap();
+        assert_eq!(flaky.store.blocks.borrow().len(), stored);
+        assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"offline");
+        assert!(queue.pending().blocks > 0);
+        root
+    };
+
+    let queue = UploadQueue::open(&dir).unwrap();
+    let pending = queue.pending();
+    assert!(pending.blocks > 0 && pending.bytes > 0);
+    let late = (Cid::new_v1(0x55, Code::Sha2_256.digest(b"late")).to_bytes(), b"late".to_vec());
+    let syncing = QueueingStore { store: flaky.clone(), queue: &queue, late };
+    assert_eq!(queue.drain(&syncing).unwrap(), pending.blocks);
+    // The block queued during the drain waits for the next one.
+    assert_eq!(queue.pending().blocks, 1);
+    assert_eq!(queue.drain(&flaky).unwrap(), 1);
+    assert_eq!(queue.pending().blocks, 0);
+
+    let mut drive = WnfsDrive::load(flaky.store.clone(), root, wnfs_key()).unwrap();
+    assert_eq!(drive.read(&path("root/a.txt")).unwrap(), b"offline");
+    assert_eq!(drive.read(&path("root/b.txt")).unwrap(), b"online");
+    drop(queue);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn car_export_of_forest_and_subtree() {
+    let dir = temp_dir("car-export");
+    let store = MemoryStore::default();
+    let (mut drive, _) = WnfsDrive::init(store.clone(), wnfs_key()).unwrap();
+    drive.write(&path("root/docs/a.txt"), b"inside".to_vec()).unwrap();
+    let cid = drive.write(&path("root/b.txt"), vec![7u8; 300_000]).unwrap();
+    drop(drive);
+
+    let full = dir.join("forest.car");
+    let blocks = export_car(store.clone(), cid, &full, None).unwrap();
+    let (root, exported) = read_car(&full);
+    assert_eq!(root, cid);
+    assert_eq!(exported.blocks.borrow().len() as u64, blocks);
+    let mut drive = WnfsDrive::load(exported, root, wnfs_key()).unwrap();
+    assert_eq!(drive.read(&path("root/docs/a.txt")).unwrap(), b"inside");
+    assert_eq!(drive.read(&path("root/b.txt")).unwrap(), vec![7u8; 300_000]);
+
+    let docs = dir.join("docs.car");
+    let subtree_blocks = export_car(store.clone(), cid, &docs, Some(path("root/docs").as_slice())).unwrap();
+    assert!(subtree_blocks < blocks);
+    let (root, exported) = read_car(&docs);
+    assert_eq!(root, cid);
+    let mut drive = WnfsDrive::reload(exported, root).unwrap();
+    assert_eq!(drive.read(&path("root/docs/a.txt")).unwrap(), b"inside");
+    assert!(drive.read(&path("root/b.txt")).is_err());
+
+    let missing = dir.join("missing.car");
+    assert!(export_car(store, cid, &missing, Some(path("root/nothing").as_slice())).is_err());
+    assert!(!missing.exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
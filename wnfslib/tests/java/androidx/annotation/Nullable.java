@@ -0,0 +1,5 @@
+package androidx.annotation;
+
+/** Stand-in for the AndroidX annotation so the library classes compile on a plain JDK. */
+public @interface Nullable {
+}
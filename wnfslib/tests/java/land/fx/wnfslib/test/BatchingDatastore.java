@@ -0,0 +1,67 @@
+package land.fx.wnfslib.test;
+
+import java.util.Base64;
+import java.util.concurrent.ConcurrentHashMap;
+import java.util.concurrent.atomic.AtomicInteger;
+import land.fx.wnfslib.BatchDatastore;
+
+/** Block store for the host tests that counts single-block and batched calls. */
+public final class BatchingDatastore implements BatchDatastore {
+    private final ConcurrentHashMap<String, byte[]> store = new ConcurrentHashMap<>();
+    private final AtomicInteger puts = new AtomicInteger();
+    private final AtomicInteger putManyCalls = new AtomicInteger();
+    private final AtomicInteger gets = new AtomicInteger();
+    private final AtomicInteger getManyCalls = new AtomicInteger();
+
+    @Override
+    public byte[] put(byte[] cid, byte[] data) {
+        puts.incrementAndGet();
+        store.put(Base64.getEncoder().encodeToString(cid), data);
+        return cid;
+    }
+
+    @Override
+    public byte[] get(byte[] cid) {
+        gets.incrementAndGet();
+        return store.get(Base64.getEncoder().encodeToString(cid));
+    }
+
+    @Override
+    public byte[][] putMany(byte[][] cids, byte[][] data) {
+        putManyCalls.incrementAndGet();
+        for (int i = 0; i < cids.length; i++) {
+            store.put(Base64.getEncoder().encodeToString(cids[i]), data[i]);
+        }
+        return cids;
+    }
+
+    @Override
+    public byte[][] getMany(byte[][] cids) {
+        getManyCalls.incrementAndGet();
+        byte[][] blocks = new byte[cids.length][];
+        for (int i = 0; i < cids.length; i++) {
+            blocks[i] = store.get(Base64.getEncoder().encodeToString(cids[i]));
+        }
+        return blocks;
+    }
+
+    public int puts() {
+        return puts.get();
+    }
+
+    public int putManyCalls() {
+        return putManyCalls.get();
+    }
+
+    public int gets() {
+        return gets.get();
+    }
+
+    public int getManyCalls() {
+        return getManyCalls.get();
+    }
+
+    public int size() {
+        return store.size();
+    }
+}
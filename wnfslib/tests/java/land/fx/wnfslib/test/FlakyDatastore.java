@@ -0,0 +1,33 @@
+package land.fx.wnfslib.test;
+
+import java.util.Base64;
+import java.util.concurrent.ConcurrentHashMap;
+import land.fx.wnfslib.Datastore;
+
+/** Block store for the host tests whose put throws while it is unreachable, like a network Datastore gone offline. */
+public final class FlakyDatastore implements Datastore {
+    private final ConcurrentHashMap<String, byte[]> store = new ConcurrentHashMap<>();
+    private volatile boolean failPuts = false;
+
+    @Override
+    public byte[] put(byte[] cid, byte[] data) {
+        if (failPuts) {
+            throw new IllegalStateException("Datastore unreachable");
+        }
+        store.put(Base64.getEncoder().encodeToString(cid), data);
+        return cid;
+    }
+
+    @Override
+    public byte[] get(byte[] cid) {
+        return store.get(Base64.getEncoder().encodeToString(cid));
+    }
+
+    public void setFailPuts(boolean failPuts) {
+        this.failPuts = failPuts;
+    }
+
+    public int size() {
+        return store.size();
+    }
+}
@@ -0,0 +1,33 @@
+package land.fx.wnfslib.test;
+
+import java.util.Base64;
+import java.util.concurrent.ConcurrentHashMap;
+import land.fx.wnfslib.Datastore;
+
+/** Block store for the host tests. Behaves like the appmock InMemoryDatastore. */
+public final class InMemoryDatastore implements Datastore {
+    private final ConcurrentHashMap<String, byte[]> store = new ConcurrentHashMap<>();
+    private volatile boolean offline = false;
+
+    @Override
+    public byte[] put(byte[] cid, byte[] data) {
+        store.put(Base64.getEncoder().encodeToString(cid), data);
+        return cid;
+    }
+
+    @Override
+    public byte[] get(byte[] cid) {
+        if (offline) {
+            return null;
+        }
+        return store.get(Base64.getEncoder().encodeToString(cid));
+    }
+
+    public void setOffline(boolean offline) {
+        this.offline = offline;
+    }
+
+    public int size() {
+        return store.size();
+    }
+}
@@ -0,0 +1,54 @@
+package land.fx.wnfslib.test;
+
+import java.util.Base64;
+import java.util.concurrent.ConcurrentHashMap;
+import java.util.concurrent.atomic.AtomicInteger;
+import land.fx.wnfslib.IndexedDatastore;
+
+/** Block store for the host tests that answers has() and hasMany() and counts puts. */
+public final class IndexingDatastore implements IndexedDatastore {
+    private final ConcurrentHashMap<String, byte[]> store = new ConcurrentHashMap<>();
+    private final AtomicInteger puts = new AtomicInteger();
+    private final AtomicInteger checks = new AtomicInteger();
+    private final AtomicInteger hasManyCalls = new AtomicInteger();
+
+    @Override
+    public byte[] put(byte[] cid, byte[] data) {
+        puts.incrementAndGet();
+        store.put(Base64.getEncoder().encodeToString(cid), data);
+        return cid;
+    }
+
+    @Override
+    public byte[] get(byte[] cid) {
+        return store.get(Base64.getEncoder().encodeToString(cid));
+    }
+
+    @Override
+    public boolean has(byte[] cid) {
+        checks.incrementAndGet();
+        return store.containsKey(Base64.getEncoder().encodeToString(cid));
+    }
+
+    @Override
+    public boolean[] hasMany(byte[][] cids) {
+        hasManyCalls.incrementAndGet();
+        boolean[] held = new boolean[cids.length];
+        for (int i = 0; i < cids.length; i++) {
+            held[i] = has(cids[i]);
+        }
+        return held;
+    }
+
+    public int puts() {
+        return puts.get();
+    }
+
+    public int checks() {
+        return checks.get();
+    }
+
+    public int hasManyCalls() {
+        return hasManyCalls.get();
+    }
+}
@@ -0,0 +1,44 @@
+package land.fx.wnfslib.test;
+
+import land.fx.wnfslib.CancellationToken;
+import land.fx.wnfslib.ProgressListener;
+
+/** Counts progress calls and optionally trips a token once a byte threshold is passed. */
+public final class RecordingListener implements ProgressListener {
+    private final CancellationToken token;
+    private final long cancelAfter;
+    private int calls = 0;
+    private long lastProcessed = -1;
+    private long lastTotal = -1;
+
+    public RecordingListener() {
+        this(null, Long.MAX_VALUE);
+    }
+
+    public RecordingListener(CancellationToken token, long cancelAfter) {
+        this.token = token;
+        this.cancelAfter = cancelAfter;
+    }
+
+    @Override
+    public void onProgress(long processedBytes, long totalBytes) {
+        calls++;
+        lastProcessed = processedBytes;
+        lastTotal = totalBytes;
+        if (token != null && processedBytes >= cancelAfter) {
+            token.cancel();
+        }
+    }
+
+    public int getCalls() {
+        return calls;
+    }
+
+    public long getLastProcessed() {
+        return lastProcessed;
+    }
+
+    public long getLastTotal() {
+        return lastTotal;
+    }
+}
@@ -0,0 +1,682 @@
+//! Drives every `Java_land_fx_wnfslib_Fs_*` export end-to-end against a JVM
+//! started in this process, so the JNI layer can be tested on a plain Linux
+//! machine. The result classes and `Datastore` come from `lib/src/main/java`;
+//! the test doubles are in `tests/java`. Requires a JDK (`JAVA_HOME` or `javac`
+//! on the `PATH`).
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{OnceLock, RwLock};
+
+use jni::objects::{JClass, JObject, JString, JValue};
+use jni::sys::{jbyteArray, jobject, jobjectArray, JNI_FALSE, JNI_TRUE};
+use jni::{InitArgsBuilder, JNIEnv, JNIVersion, JavaVM};
+use wnfslib::android::*;
+
+const STORE_UNAVAILABLE: i32 = 8;
+const CANCELLED: i32 = 11;
+
+fn java_home_tool(tool: &str) -> PathBuf {
+    match std::env::var_os("JAVA_HOME") {
+        Some(home) => Path::new(&home).join("bin").join(tool),
+        None => PathBuf::from(tool),
+    }
+}
+
+fn compile_java() -> PathBuf {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib = manifest.join("../lib/src/main/java/land/fx/wnfslib");
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("jni-host-classes");
+    std::fs::create_dir_all(&out).unwrap();
+
+    let mut sources: Vec<PathBuf> = ["Config.java", "Datastore.java", "BatchDatastore.java", "IndexedDatastore.java", "ProgressListener.java", "CancellationToken.java"]
+        .iter()
+        .map(|name| lib.join(name))
+        .collect();
+    let mut dirs = vec![lib.join("result"), manifest.join("tests/java")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().map_or(false, |ext| ext == "java") {
+                sources.push(path);
+            }
+        }
+    }
+
+    let status = Command::new(java_home_tool("javac"))
+        .arg("-d")
+        .arg(&out)
+        .args(&sources)
+        .status()
+        .expect("running javac; is a JDK installed?");
+    assert!(status.success(), "javac failed");
+    out
+}
+
+fn jvm() -> &'static JavaVM {
+    static JVM: OnceLock<JavaVM> = OnceLock::new();
+    JVM.get_or_init(|| {
+        let classes = compile_java();
+        let args = InitArgsBuilder::new()
+            .version(JNIVersion::V8)
+            .option(&format!("-Djava.class.path={}", classes.display()))
+            .build()
+            .expect("building JVM arguments");
+        JavaVM::new(args).expect("starting JVM")
+    })
+}
+
+/// The disk cache and the upload queue apply to every call in the process.
+/// Tests that enable them hold this for writing; every other test holds it
+/// for reading, so they never run while those are enabled.
+static GLOBAL_STATE: RwLock<()> = RwLock::new(());
+
+/// Runs `f` on a thread attached to the shared JVM.
+fn with_env(f: impl FnOnce(JNIEnv)) {
+    let _shared = GLOBAL_STATE.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    attached(f);
+}
+
+/// Like `with_env`, for tests that change the process-wide disk cache or
+/// upload queue. They must be disabled again before `f` returns.
+fn with_global_state(f: impl FnOnce(JNIEnv)) {
+    let _exclusive = GLOBAL_STATE.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    attached(f);
+}
+
+fn attached(f: impl FnOnce(JNIEnv)) {
+    let guard = jvm().attach_current_thread().expect("attaching to JVM");
+    Java_land_fx_wnfslib_Fs_initRustLogger(*guard, class());
+    f(*guard);
+}
+
+fn class<'a>() -> JClass<'a> {
+    JObject::null().into()
+}
+
+fn jstr<'a>(env: JNIEnv<'a>, text: &str) -> JString<'a> {
+    env.new_string(text).unwrap()
+}
+
+fn jbytes(env: JNIEnv, bytes: &[u8]) -> jbyteArray {
+    env.byte_array_from_slice(bytes).unwrap()
+}
+
+fn new_object<'a>(env: JNIEnv<'a>, class: &str) -> JObject<'a> {
+    env.new_object(class, "()V", &[]).unwrap()
+}
+
+fn datastore<'a>(env: JNIEnv<'a>) -> JObject<'a> {
+    new_object(env, "land/fx/wnfslib/test/InMemoryDatastore")
+}
+
+fn wnfs_key() -> Vec<u8> {
+    // Any 32 bytes will do; the appmock tests use sha256("test").
+    (0u8..32).collect()
+}
+
+/// Unpacks a `TypedResult`: `Ok(result)` or `Err((code, reason))`.
+fn unwrap_result<'a>(env: JNIEnv<'a>, result: jobject) -> Result<JObject<'a>, (i32, String)> {
+    let result = JObject::from(result);
+    assert!(!result.is_null(), "native returned a null result object");
+    assert!(!env.exception_check().unwrap(), "native left a Java exception pending");
+    let ok = env
+        .call_method(result, "ok", "()Ljava/lang/Boolean;", &[])
+        .and_then(|ok| ok.l())
+        .and_then(|ok| env.call_method(ok, "booleanValue", "()Z", &[]))
+        .and_then(|ok| ok.z())
+        .unwrap();
+    if ok {
+        Ok(env.call_method(result, "getResult", "()Ljava/lang/Object;", &[]).unwrap().l().unwrap())
+    } else {
+        let code = env.call_method(result, "getCode", "()I", &[]).unwrap().i().unwrap();
+        let reason = env.call_method(result, "getReason", "()Ljava/lang/String;", &[]).unwrap().l().unwrap();
+        Err((code, string(env, reason)))
+    }
+}
+
+fn ok<'a>(env: JNIEnv<'a>, result: jobject) -> JObject<'a> {
+    unwrap_result(env, result).unwrap_or_else(|(code, reason)| panic!("native call failed ({}): {}", code, reason))
+}
+
+fn err(env: JNIEnv, result: jobject) -> (i32, String) {
+    match unwrap_result(env, result) {
+        Ok(_) => panic!("native call unexpectedly succeeded"),
+        Err(err) => err,
+    }
+}
+
+fn string(env: JNIEnv, obj: JObject) -> String {
+    env.get_string(JString::from(obj)).unwrap().into()
+}
+
+fn bytes(env: JNIEnv, obj: JObject) -> Vec<u8> {
+    env.convert_byte_array(obj.into_inner()).unwrap()
+}
+
+/// The forest CID held by a `ConfigResult`.
+fn cid(env: JNIEnv, result: jobject) -> String {
+    let config = ok(env, result);
+    let cid = env.call_method(config, "getCid", "()Ljava/lang/String;", &[]).unwrap().l().unwrap();
+    string(env, cid)
+}
+
+fn json(env: JNIEnv, result: jobject) -> serde_json::Value {
+    serde_json::from_slice(&bytes(env, ok(env, result))).unwrap()
+}
+
+fn init(env: JNIEnv, store: JObject) -> String {
+    cid(env, Java_land_fx_wnfslib_Fs_initNative(env, class(), store, jbytes(env, &wnfs_key())))
+}
+
+fn write(env: JNIEnv, store: JObject, root: &str, path: &str, content: &[u8]) -> String {
+    cid(
+        env,
+        Java_land_fx_wnfslib_Fs_writeFileNative(env, class(), store, jstr(env, root), jstr(env, path), jbytes(env, content)),
+    )
+}
+
+fn read(env: JNIEnv, store: JObject, root: &str, path: &str) -> Vec<u8> {
+    let result = Java_land_fx_wnfslib_Fs_readFileNative(env, class(), store, jstr(env, root), jstr(env, path));
+    bytes(env, ok(env, result))
+}
+
+fn ls_names(env: JNIEnv, store: JObject, root: &str, path: &str) -> Vec<String> {
+    let entries = json(env, Java_land_fx_wnfslib_Fs_lsNative(env, class(), store, jstr(env, root), jstr(env, path)));
+    let mut names: Vec<String> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["name"].as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+fn exists(env: JNIEnv, store: JObject, root: &str, path: &str) -> String {
+    let result = Java_land_fx_wnfslib_Fs_existsNative(env, class(), store, jstr(env, root), jstr(env, path));
+    string(env, ok(env, result))
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn init_load_and_basic_operations() {
+    with_env(|env| {
+        let store = datastore(env);
+        let mut root = init(env, store);
+
+        let loaded = Java_land_fx_wnfslib_Fs_loadWithWNFSKeyNative(env, class(), store, jbytes(env, &wnfs_key()), jstr(env, &root));
+        ok(env, loaded);
+
+        root = cid(env, Java_land_fx_wnfslib_Fs_mkdirNative(env, class(), store, jstr(env, &root), jstr(env, "root/album")));
+        root = write(env, store, &root, "root/album/a.txt", b"Hello, World!");
+        root = cid(
+            env,
+            Java_land_fx_wnfslib_Fs_cpNative(env, class(), store, jstr(env, &root), jstr(env, "root/album/a.txt"), jstr(env, "root/album/b.txt")),
+        );
+        root = cid(
+            env,
+            Java_land_fx_wnfslib_Fs_mvNative(env, class(), store, jstr(env, &root), jstr(env, "root/album/b.txt"), jstr(env, "root/album/c.txt")),
+        );
+        assert_eq!(ls_names(env, store, &root, "root/album"), ["a.txt", "c.txt"]);
+        assert_eq!(read(env, store, &root, "root/album/c.txt"), b"Hello, World!");
+
+        root = cid(env, Java_land_fx_wnfslib_Fs_rmNative(env, class(), store, jstr(env, &root), jstr(env, "root/album/a.txt")));
+        assert_eq!(ls_names(env, store, &root, "root/album"), ["c.txt"]);
+
+        let (code, reason) = err(env, Java_land_fx_wnfslib_Fs_readFileNative(env, class(), store, jstr(env, &root), jstr(env, "root/album/a.txt")));
+        assert_eq!(code, 1, "{}", reason);
+    });
+}
+
+#[test]
+fn stat_exists_and_ranges() {
+    with_env(|env| {
+        let store = datastore(env);
+        let mut root = init(env, store);
+        let content: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        root = write(env, store, &root, "root/video.bin", &content);
+
+        let stat = json(env, Java_land_fx_wnfslib_Fs_statNative(env, class(), store, jstr(env, &root), jstr(env, "root/video.bin")));
+        assert_eq!(stat["kind"], "file");
+        assert_eq!(stat["size"], content.len() as u64);
+        assert!(stat["cid"].is_string());
+
+        assert_eq!(exists(env, store, &root, "root/video.bin"), "file");
+        assert_eq!(exists(env, store, &root, "root"), "directory");
+        assert_eq!(exists(env, store, &root, "root/missing"), "none");
+
+        let range = Java_land_fx_wnfslib_Fs_readFileRangeNative(env, class(), store, jstr(env, &root), jstr(env, "root/video.bin"), 1_000_000, 4096);
+        assert_eq!(bytes(env, ok(env, range)), &content[1_000_000..1_004_096]);
+    });
+}
+
+#[test]
+fn local_file_transfers() {
+    with_env(|env| {
+        let store = datastore(env);
+        let mut root = init(env, store);
+        let dir = temp_dir("local-file-transfers");
+        let source = dir.join("source.bin");
+        let content: Vec<u8> = (0..2 * 1024 * 1024).map(|i| (i % 253) as u8).collect();
+        std::fs::write(&source, &content).unwrap();
+        let source = source.to_str().unwrap();
+
+        root = cid(
+            env,
+            Java_land_fx_wnfslib_Fs_writeFileFromPathNative(env, class(), store, jstr(env, &root), jstr(env, "root/a.bin"), jstr(env, source)),
+        );
+        let listener = new_object(env, "land/fx/wnfslib/test/RecordingListener");
+        root = cid(
+            env,
+            Java_land_fx_wnfslib_Fs_writeFileStreamFromPathNative(
+                env,
+                class(),
+                store,
+                jstr(env, &root),
+                jstr(env, "root/b.bin"),
+                jstr(env, source),
+                listener,
+                0,
+                JObject::null(),
+            ),
+        );
+        let calls = env.call_method(listener, "getCalls", "()I", &[]).unwrap().i().unwrap();
+        assert!(calls > 1);
+
+        let target = dir.join("a.out");
+        let result = Java_land_fx_wnfslib_Fs_readFileToPathNative(env, class(), store, jstr(env, &root), jstr(env, "root/a.bin"), jstr(env, target.to_str().unwrap()));
+        ok(env, result);
+        assert_eq!(std::fs::read(&target).unwrap(), content);
+
+        let target = dir.join("b.out");
+        std::fs::write(&target, &content[..1000]).unwrap();
+        let result = Java_land_fx_wnfslib_Fs_readFilestreamToPathNative(
+            env,
+            class(),
+            store,
+            jstr(env, &root),
+            jstr(env, "root/b.bin"),
+            jstr(env, target.to_str().unwrap()),
+            1000,
+            JObject::null(),
+            0,
+            JObject::null(),
+        );
+        ok(env, result);
+        assert_eq!(std::fs::read(&target).unwrap(), content);
+
+        // A tripped token cancels the upload and leaves the old root untouched
+        let token = new_object(env, "land/fx/wnfslib/CancellationToken");
+        let cancelling = env
+            .new_object(
+                "land/fx/wnfslib/test/RecordingListener",
+                "(Lland/fx/wnfslib/CancellationToken;J)V",
+                &[JValue::Object(token), JValue::Long(1024 * 1024)],
+            )
+            .unwrap();
+        let (code, _) = err(
+            env,
+            Java_land_fx_wnfslib_Fs_writeFileStreamFromPathNative(
+                env,
+                class(),
+                store,
+                jstr(env, &root),
+                jstr(env, "root/c.bin"),
+                jstr(env, source),
+                cancelling,
+                0,
+                token,
+            ),
+        );
+        assert_eq!(code, CANCELLED);
+        assert_eq!(exists(env, store, &root, "root/c.bin"), "none");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+#[test]
+fn directory_transfers() {
+    with_env(|env| {
+        let store = datastore(env);
+        let mut root = init(env, store);
+        let dir = temp_dir("directory-transfers");
+        let upload = dir.join("upload");
+        std::fs::create_dir_all(upload.join("2024/trip")).unwrap();
+        std::fs::write(upload.join("a.jpg"), b"a").unwrap();
+        std::fs::write(upload.join("2024/trip/b.jpg"), b"bb").unwrap();
+
+        let report = json(
+            env,
+            Java_land_fx_wnfslib_Fs_writeDirFromPathNative(
+                env,
+                class(),
+                store,
+                jstr(env, &root),
+                jstr(env, "root/camera"),
+                jstr(env, upload.to_str().unwrap()),
+                JNI_TRUE,
+                JNI_FALSE,
+            ),
+        );
+        assert_eq!(report["succeeded"].as_array().unwrap().len(), 2);
+        root = report["cid"].as_str().unwrap().to_string();
+
+        let download = dir.join("download");
+        let report = json(
+            env,
+            Java_land_fx_wnfslib_Fs_readDirToPathNative(env, class(), store, jstr(env, &root), jstr(env, "root/camera"), jstr(env, download.to_str().unwrap())),
+        );
+        assert!(report["failed"].as_array().unwrap().is_empty());
+        assert_eq!(std::fs::read(download.join("2024/trip/b.jpg")).unwrap(), b"bb");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+fn string_array(env: JNIEnv, items: &[Option<&str>]) -> jobjectArray {
+    let array = env.new_object_array(items.len() as i32, "java/lang/String", JObject::null()).unwrap();
+    for (index, item) in items.iter().enumerate() {
+        let value = item.map_or(JObject::null(), |item| jstr(env, item).into());
+        env.set_object_array_element(array, index as i32, value).unwrap();
+    }
+    array
+}
+
+#[test]
+fn transactions() {
+    with_env(|env| {
+        let store = datastore(env);
+        let root = init(env, store);
+
+        let kinds = string_array(env, &[Some("mkdir"), Some("writeFile"), Some("mv")]);
+        let paths = string_array(env, &[Some("root/album"), Some("root/album/1.jpg"), Some("root/album/1.jpg")]);
+        let targets = string_array(env, &[None, None, Some("root/album/cover.jpg")]);
+        let contents = env.new_object_array(3, "[B", JObject::null()).unwrap();
+        env.set_object_array_element(contents, 1, JObject::from(jbytes(env, b"photo"))).unwrap();
+        let committed = cid(
+            env,
+            Java_land_fx_wnfslib_Fs_commitTransactionNative(env, class(), store, jstr(env, &root), kinds, paths, targets, contents),
+        );
+        assert_eq!(ls_names(env, store, &committed, "root/album"), ["cover.jpg"]);
+
+        let kinds = string_array(env, &[Some("writeFile"), Some("rm")]);
+        let paths = string_array(env, &[Some("root/album/2.jpg"), Some("root/album/missing.jpg")]);
+        let targets = string_array(env, &[None, None]);
+        let contents = env.new_object_array(2, "[B", JObject::null()).unwrap();
+        env.set_object_array_element(contents, 0, JObject::from(jbytes(env, b"photo"))).unwrap();
+        let (_, reason) = err(
+            env,
+            Java_land_fx_wnfslib_Fs_commitTransactionNative(env, class(), store, jstr(env, &committed), kinds, paths, targets, contents),
+        );
+        assert!(reason.contains("nothing was committed"), "{}", reason);
+        assert_eq!(exists(env, store, &committed, "root/album/2.jpg"), "none");
+    });
+}
+
+#[test]
+fn sessions() {
+    with_env(|env| {
+        let store = datastore(env);
+        let root = init(env, store);
+
+        let handle = ok(env, Java_land_fx_wnfslib_Fs_openSessionNative(env, class(), store, jstr(env, &root), jbytes(env, &wnfs_key()), 0, JNI_FALSE));
+        let handle = env.call_method(handle, "longValue", "()J", &[]).unwrap().j().unwrap();
+
+        cid(env, Java_land_fx_wnfslib_Fs_mkdirSessionNative(env, class(), handle, jstr(env, "root/album")));
+        cid(env, Java_land_fx_wnfslib_Fs_writeFileSessionNative(env, class(), handle, jstr(env, "root/album/a.txt"), jbytes(env, b"session")));
+        cid(env, Java_land_fx_wnfslib_Fs_cpSessionNative(env, class(), handle, jstr(env, "root/album/a.txt"), jstr(env, "root/album/b.txt")));
+        cid(env, Java_land_fx_wnfslib_Fs_mvSessionNative(env, class(), handle, jstr(env, "root/album/b.txt"), jstr(env, "root/album/c.txt")));
+        let last = cid(env, Java_land_fx_wnfslib_Fs_rmSessionNative(env, class(), handle, jstr(env, "root/album/a.txt")));
+
+        let content = ok(env, Java_land_fx_wnfslib_Fs_readFileSessionNative(env, class(), handle, jstr(env, "root/album/c.txt")));
+        assert_eq!(bytes(env, content), b"session");
+        let entries = json(env, Java_land_fx_wnfslib_Fs_lsSessionNative(env, class(), handle, jstr(env, "root/album")));
+        assert_eq!(entries.as_array().unwrap().len(), 1);
+
+        ok(env, Java_land_fx_wnfslib_Fs_closeSessionNative(env, class(), handle));
+        err(env, Java_land_fx_wnfslib_Fs_closeSessionNative(env, class(), handle));
+        assert_eq!(read(env, store, &last, "root/album/c.txt"), b"session");
+    });
+}
+
+/// Runs the same edits in a session and returns the blocks they added to the Datastore.
+fn session_edits(env: JNIEnv, write_back: bool) -> (i32, i32) {
+    let store = datastore(env);
+    let root = init(env, store);
+    let size = || env.call_method(store, "size", "()I", &[]).unwrap().i().unwrap();
+    let before = size();
+    let write_back = if write_back { JNI_TRUE } else { JNI_FALSE };
+    let handle = ok(env, Java_land_fx_wnfslib_Fs_openSessionNative(env, class(), store, jstr(env, &root), jbytes(env, &wnfs_key()), 0, write_back));
+    let handle = env.call_method(handle, "longValue", "()J", &[]).unwrap().j().unwrap();
+
+    cid(env, Java_land_fx_wnfslib_Fs_mkdirSessionNative(env, class(), handle, jstr(env, "root/album")));
+    for draft in ["one", "two", "three"] {
+        cid(env, Java_land_fx_wnfslib_Fs_writeFileSessionNative(env, class(), handle, jstr(env, "root/album/a.txt"), jbytes(env, draft.as_bytes())));
+    }
+    let before_flush = size();
+    let content = ok(env, Java_land_fx_wnfslib_Fs_readFileSessionNative(env, class(), handle, jstr(env, "root/album/a.txt")));
+    assert_eq!(bytes(env, content), b"three");
+
+    let flushed = cid(env, Java_land_fx_wnfslib_Fs_flushSessionNative(env, class(), handle));
+    ok(env, Java_land_fx_wnfslib_Fs_closeSessionNative(env, class(), handle));
+    assert_eq!(read(env, store, &flushed, "root/album/a.txt"), b"three");
+    (before_flush - before, size() - before)
+}
+
+#[test]
+fn write_back_session() {
+    with_env(|env| {
+        let (before_flush, written_through) = session_edits(env, false);
+        assert!(before_flush > 0);
+        let (before_flush, written_back) = session_edits(env, true);
+        assert_eq!(before_flush, 0);
+        assert!(written_back < written_through, "{} >= {}", written_back, written_through);
+    });
+}
+
+#[test]
+fn session_block_cache() {
+    with_env(|env| {
+        let store = datastore(env);
+        let root = write(env, store, &init(env, store), "root/a.txt", b"cached");
+
+        let handle = ok(env, Java_land_fx_wnfslib_Fs_openSessionNative(env, class(), store, jstr(env, &root), jbytes(env, &wnfs_key()), 1 << 20, JNI_FALSE));
+        let handle = env.call_method(handle, "longValue", "()J", &[]).unwrap().j().unwrap();
+        let first = ok(env, Java_land_fx_wnfslib_Fs_readFileSessionNative(env, class(), handle, jstr(env, "root/a.txt")));
+        assert_eq!(bytes(env, first), b"cached");
+
+        // Everything the first read fetched is now served without the Datastore.
+        env.call_method(store, "setOffline", "(Z)V", &[JValue::Bool(JNI_TRUE)]).unwrap();
+        let second = ok(env, Java_land_fx_wnfslib_Fs_readFileSessionNative(env, class(), handle, jstr(env, "root/a.txt")));
+        assert_eq!(bytes(env, second), b"cached");
+
+        let stats = json(env, Java_land_fx_wnfslib_Fs_cacheStatsSessionNative(env, class(), handle));
+        assert_eq!(stats["capacity"], 1 << 20);
+        assert!(stats["hits"].as_u64().unwrap() > 0);
+        assert!(stats["misses"].as_u64().unwrap() > 0);
+        assert!(stats["size"].as_u64().unwrap() <= 1 << 20);
+        ok(env, Java_land_fx_wnfslib_Fs_closeSessionNative(env, class(), handle));
+        err(env, Java_land_fx_wnfslib_Fs_cacheStatsSessionNative(env, class(), handle));
+    });
+}
+
+#[test]
+fn batched_datastore() {
+    with_env(|env| {
+        let store = new_object(env, "land/fx/wnfslib/test/BatchingDatastore");
+        let count = |method: &str| env.call_method(store, method, "()I", &[]).unwrap().i().unwrap();
+        let content: Vec<u8> = (0..2 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let root = write(env, store, &init(env, store), "root/big.bin", &content);
+
+        assert_eq!(count("puts"), 0);
+        assert!(count("putManyCalls") > 0);
+        assert!(count("putManyCalls") < count("size"));
+        assert_eq!(read(env, store, &root, "root/big.bin"), content);
+
+        // A full CAR export walks the forest a batch of links at a time.
+        let dir = temp_dir("jni-batched-car");
+        let output = dir.join("forest.car");
+        let result = Java_land_fx_wnfslib_Fs_exportCarNative(env, class(), store, jstr(env, &root), jstr(env, output.to_str().unwrap()), JString::from(JObject::null()));
+        let blocks = env.call_method(ok(env, result), "longValue", "()J", &[]).unwrap().j().unwrap();
+        assert!(count("getManyCalls") > 0);
+        assert!((count("getManyCalls") as i64) < blocks, "{} {}", count("getManyCalls"), blocks);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // A plain Datastore still gets one put per block.
+        let plain = datastore(env);
+        let root = write(env, plain, &init(env, plain), "root/a.txt", b"plain");
+        assert_eq!(read(env, plain, &root, "root/a.txt"), b"plain");
+    });
+}
+
+#[test]
+fn put_policy() {
+    with_env(|env| {
+        let store = new_object(env, "land/fx/wnfslib/test/IndexingDatastore");
+        let count = |method: &str| env.call_method(store, method, "()I", &[]).unwrap().i().unwrap();
+        let root = write(env, store, &init(env, store), "root/a.txt", b"first");
+        assert_eq!(count("checks"), 0);
+
+        ok(env, Java_land_fx_wnfslib_Fs_setPutPolicyNative(env, class(), 1));
+        let before = json(env, Java_land_fx_wnfslib_Fs_putStatsNative(env, class()));
+        let root = write(env, store, &root, "root/b.txt", b"second");
+        let after = json(env, Java_land_fx_wnfslib_Fs_putStatsNative(env, class()));
+        ok(env, Java_land_fx_wnfslib_Fs_setPutPolicyNative(env, class(), 0));
+
+        assert!(count("checks") > 0);
+        assert!(0 < count("hasManyCalls") && count("hasManyCalls") < count("checks"));
+        assert!(after["checks"].as_u64().unwrap() >= before["checks"].as_u64().unwrap() + count("checks") as u64);
+        assert_eq!(read(env, store, &root, "root/a.txt"), b"first");
+        assert_eq!(read(env, store, &root, "root/b.txt"), b"second");
+
+        let (code, reason) = err(env, Java_land_fx_wnfslib_Fs_setPutPolicyNative(env, class(), 7));
+        assert_eq!(code, 0);
+        assert!(reason.contains("invalid put policy"), "{}", reason);
+    });
+}
+
+#[test]
+fn car_export() {
+    with_env(|env| {
+        let dir = temp_dir("jni-car");
+        let store = datastore(env);
+        let root = write(env, store, &init(env, store), "root/docs/a.txt", b"inside");
+        let root = write(env, store, &root, "root/b.txt", &vec![7u8; 300_000]);
+        let export = |name: &str, subtree: JString| {
+            let output = dir.join(name);
+            let result = Java_land_fx_wnfslib_Fs_exportCarNative(env, class(), store, jstr(env, &root), jstr(env, output.to_str().unwrap()), subtree);
+            let blocks = env.call_method(ok(env, result), "longValue", "()J", &[]).unwrap().j().unwrap();
+            (blocks, std::fs::metadata(&output).unwrap().len())
+        };
+
+        let (blocks, size) = export("forest.car", JString::from(JObject::null()));
+        let (subtree_blocks, subtree_size) = export("docs.car", jstr(env, "root/docs"));
+        assert!(0 < subtree_blocks && subtree_blocks < blocks, "{} {}", subtree_blocks, blocks);
+        assert!(size > 300_000 && subtree_size < 300_000, "{} {}", size, subtree_size);
+
+        let missing = dir.join("missing.car");
+        let result = Java_land_fx_wnfslib_Fs_exportCarNative(env, class(), store, jstr(env, &root), jstr(env, missing.to_str().unwrap()), jstr(env, "root/nothing"));
+        err(env, result);
+        assert!(!missing.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+#[test]
+fn disk_cache() {
+    with_global_state(|env| {
+        let dir = temp_dir("jni-disk-cache");
+        let store = datastore(env);
+        let root = write(env, store, &init(env, store), "root/a.txt", b"cached");
+        let stats = || json(env, Java_land_fx_wnfslib_Fs_diskCacheStatsNative(env, class()));
+
+        ok(env, Java_land_fx_wnfslib_Fs_enableDiskCacheNative(env, class(), jstr(env, dir.to_str().unwrap()), 1 << 20, jbytes(env, &wnfs_key())));
+        assert_eq!(read(env, store, &root, "root/a.txt"), b"cached");
+        assert_eq!(stats()["capacity"], 1 << 20);
+        assert!(stats()["misses"].as_u64().unwrap() > 0);
+        assert!(stats()["size"].as_u64().unwrap() > 0);
+
+        // Everything the first read fetched is now on disk.
+        env.call_method(store, "setOffline", "(Z)V", &[JValue::Bool(JNI_TRUE)]).unwrap();
+        assert_eq!(read(env, store, &root, "root/a.txt"), b"cached");
+        assert!(stats()["hits"].as_u64().unwrap() > 0);
+
+        ok(env, Java_land_fx_wnfslib_Fs_clearCacheNative(env, class()));
+        assert_eq!(stats()["entries"], 0);
+        let (code, _) = err(env, Java_land_fx_wnfslib_Fs_readFileNative(env, class(), store, jstr(env, &root), jstr(env, "root/a.txt")));
+        assert_eq!(code, STORE_UNAVAILABLE);
+
+        ok(env, Java_land_fx_wnfslib_Fs_disableDiskCacheNative(env, class()));
+        assert_eq!(stats()["capacity"], 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+#[test]
+fn upload_queue() {
+    with_global_state(|env| {
+        let dir = temp_dir("jni-upload-queue");
+        let store = new_object(env, "land/fx/wnfslib/test/FlakyDatastore");
+        let fail_puts = |fail: bool| {
+            let fail = if fail { JNI_TRUE } else { JNI_FALSE };
+            env.call_method(store, "setFailPuts", "(Z)V", &[JValue::Bool(fail)]).unwrap();
+        };
+        let pending = || json(env, Java_land_fx_wnfslib_Fs_pendingUploadsNative(env, class()));
+        let sync = || unwrap_result(env, Java_land_fx_wnfslib_Fs_syncPendingNative(env, class(), store));
+        let root = init(env, store);
+
+        ok(env, Java_land_fx_wnfslib_Fs_enableUploadQueueNative(env, class(), jstr(env, dir.to_str().unwrap())));
+        // put throws, which counts as unreachable: the write succeeds and its blocks wait on disk.
+        fail_puts(true);
+        let root = write(env, store, &root, "root/a.txt", b"queued");
+        let queued = pending()["blocks"].as_u64().unwrap();
+        assert!(queued > 0);
+        assert!(pending()["bytes"].as_u64().unwrap() > 0);
+        assert_eq!(read(env, store, &root, "root/a.txt"), b"queued");
+
+        let (code, _) = sync().map(|_| ()).unwrap_err();
+        assert_eq!(code, STORE_UNAVAILABLE);
+        assert_eq!(pending()["blocks"].as_u64().unwrap(), queued);
+
+        fail_puts(false);
+        let sent = env.call_method(sync().unwrap(), "longValue", "()J", &[]).unwrap().j().unwrap();
+        assert_eq!(sent as u64, queued);
+        assert_eq!(pending()["blocks"], 0);
+
+        ok(env, Java_land_fx_wnfslib_Fs_disableUploadQueueNative(env, class()));
+        assert_eq!(read(env, store, &root, "root/a.txt"), b"queued");
+        // Without the queue the failed put reaches Java.
+        fail_puts(true);
+        let result = Java_land_fx_wnfslib_Fs_writeFileNative(env, class(), store, jstr(env, &root), jstr(env, "root/b.txt"), jbytes(env, b"lost"));
+        let (code, _) = err(env, result);
+        assert_eq!(code, STORE_UNAVAILABLE);
+        std::fs::remove_dir_all(&dir).unwrap();
+    });
+}
+
+#[test]
+fn errors_reach_java_as_result_objects() {
+    with_env(|env| {
+        let store = datastore(env);
+        let root = init(env, store);
+        let root = write(env, store, &root, "root/a.txt", b"a");
+
+        let (code, _) = err(env, Java_land_fx_wnfslib_Fs_readFileNative(env, class(), store, jstr(env, "not a cid"), jstr(env, "root/a.txt")));
+        assert_eq!(code, 6);
+        err(env, Java_land_fx_wnfslib_Fs_readFileNative(env, class(), store, JString::from(JObject::null()), JString::from(JObject::null())));
+
+        env.call_method(store, "setOffline", "(Z)V", &[JValue::Bool(JNI_TRUE)]).unwrap();
+        let (code, _) = err(env, Java_land_fx_wnfslib_Fs_readFileNative(env, class(), store, jstr(env, &root), jstr(env, "root/a.txt")));
+        assert_eq!(code, STORE_UNAVAILABLE);
+    });
+}